@@ -1,9 +1,10 @@
+use std::collections::HashSet;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
 
 use clap::Parser;
-use rnadraw_core::svg::{DEFAULT_NUCLEOTIDE_COLORS, Legend, SvgOptions};
+use rnadraw_core::svg::{self, Legend, SvgOptions};
 
 #[derive(Clone, Copy, clap::ValueEnum)]
 enum Format {
@@ -11,13 +12,54 @@ enum Format {
     Json,
 }
 
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Colormap {
+    Equilibrium,
+    Viridis,
+    Plasma,
+    Grayscale,
+    Coolwarm,
+}
+
+impl From<Colormap> for svg::Colormap {
+    fn from(c: Colormap) -> Self {
+        match c {
+            Colormap::Equilibrium => svg::Colormap::Equilibrium,
+            Colormap::Viridis => svg::Colormap::Viridis,
+            Colormap::Plasma => svg::Colormap::Plasma,
+            Colormap::Grayscale => svg::Colormap::Grayscale,
+            Colormap::Coolwarm => svg::Colormap::Coolwarm,
+        }
+    }
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Palette {
+    Default,
+    ColorblindSafe,
+}
+
+impl From<Palette> for svg::NucleotidePalette {
+    fn from(p: Palette) -> Self {
+        match p {
+            Palette::Default => svg::NucleotidePalette::Default,
+            Palette::ColorblindSafe => svg::NucleotidePalette::ColorblindSafe,
+        }
+    }
+}
+
 /// RNA secondary structure SVG renderer
 #[derive(Parser)]
 #[command(name = "rnadraw", version)]
 struct Cli {
     /// Dot-bracket-plus structure notation
-    #[arg(short, long)]
-    structure: String,
+    #[arg(short, long, required_unless_present = "dbn")]
+    structure: Option<String>,
+
+    /// Render every `>header` / sequence / structure record of a .dbn file to
+    /// `<header>.svg` in the --output directory (default: current directory)
+    #[arg(long, conflicts_with_all = ["structure", "sequence"])]
+    dbn: Option<PathBuf>,
 
     /// RNA sequence (e.g. GGGAAACCC)
     #[arg(short = 'q', long)]
@@ -32,11 +74,28 @@ struct Cli {
     #[arg(short, long, value_delimiter = ',', conflicts_with = "nucleotide")]
     probabilities: Option<Vec<f64>>,
 
-    /// Color by nucleotide type (A=green, U=red, G=black, C=blue).
-    /// Used when --probabilities is not given.
+    /// Color by nucleotide type (A=green, U=red, G=black, C=blue, see --palette).
+    /// Used when --probabilities is not given. Without any color mode, bases
+    /// share one uniform fill.
     #[arg(short = 'c', long)]
     nucleotide: bool,
 
+    /// Palette for --nucleotide (colorblind-safe uses Okabe–Ito colors)
+    #[arg(long, value_enum, default_value_t = Palette::Default)]
+    palette: Palette,
+
+    /// Color by sequence position (5' → 3' gradient).
+    #[arg(long, conflicts_with_all = ["probabilities", "nucleotide"])]
+    position: bool,
+
+    /// Colormap for --probabilities and --position
+    #[arg(long, value_enum, default_value_t = Colormap::Equilibrium)]
+    colormap: Colormap,
+
+    /// Print-safe black-and-white output (grayscale data, hatched nucleotides)
+    #[arg(long)]
+    monochrome: bool,
+
     /// Hide 3' direction arrows
     #[arg(long)]
     no_arrows: bool,
@@ -45,7 +104,7 @@ struct Cli {
     #[arg(long)]
     no_align: bool,
 
-    /// Output file (default: stdout)
+    /// Output file (default: stdout), or output directory with --dbn
     #[arg(short, long)]
     output: Option<PathBuf>,
 }
@@ -53,52 +112,254 @@ struct Cli {
 fn main() {
     let cli = Cli::parse();
 
-    let output = match cli.format {
+    if let Some(path) = &cli.dbn {
+        render_dbn(&cli, path);
+        return;
+    }
+
+    let structure = cli.structure.as_deref().unwrap_or_default();
+    let output = match render(&cli, structure, cli.sequence.as_deref()) {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("error: {e}");
+            process::exit(1);
+        }
+    };
+
+    if let Some(path) = &cli.output {
+        if let Err(e) = std::fs::write(path, &output) {
+            eprintln!("error: failed to write {}: {e}", path.display());
+            process::exit(1);
+        }
+    } else {
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        if let Err(e) = handle.write_all(output.as_bytes()) {
+            eprintln!("error: write failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Render one structure in the requested format with the color options from `cli`.
+fn render(cli: &Cli, structure: &str, sequence: Option<&str>) -> Result<String, String> {
+    match cli.format {
         Format::Json => {
-            let json = rnadraw_core::draw_structure(&cli.structure);
+            let json = rnadraw_core::draw_structure(structure);
             if json.is_empty() {
-                eprintln!("error: invalid structure or empty result");
-                process::exit(1);
+                return Err("invalid structure or empty result".into());
             }
-            json
+            Ok(json)
         }
         Format::Svg => {
             let mut opts = SvgOptions {
                 show_arrows: !cli.no_arrows,
                 align_stem: !cli.no_align,
+                colormap: cli.colormap.into(),
+                monochrome: cli.monochrome,
                 ..SvgOptions::default()
             };
 
             if cli.probabilities.is_some() {
-                opts.probabilities = cli.probabilities;
+                opts.probabilities = cli.probabilities.clone();
             } else if cli.nucleotide {
-                opts.base_colors = Some(DEFAULT_NUCLEOTIDE_COLORS.map(String::from));
+                // Ambiguity codes such as N still render, so only warn
+                if let Some(Err(invalid)) =
+                    sequence.map(|seq| rnadraw_core::validate_sequence(seq, true))
+                {
+                    let (i, c) = invalid[0];
+                    eprintln!(
+                        "warning: unrecognized nucleotide {c:?} at position {} ({} in total)",
+                        i + 1,
+                        invalid.len()
+                    );
+                }
+                opts.nucleotide_palette = Some(cli.palette.into());
                 opts.legend = Legend::Nucleotide;
-            } else {
-                eprintln!("error: specify --probabilities or --nucleotide for SVG color mode");
-                process::exit(1);
+            } else if cli.position {
+                opts.position_gradient = true;
             }
 
-            let svg = rnadraw_core::draw_svg(&cli.structure, cli.sequence.as_deref(), &opts);
-            if svg.is_empty() {
-                eprintln!("error: invalid structure or empty result");
-                process::exit(1);
-            }
-            svg
+            rnadraw_core::try_draw_svg(structure, sequence, &opts).map_err(|e| e.to_string())
         }
-    };
+    }
+}
 
-    if let Some(path) = cli.output {
-        if let Err(e) = std::fs::write(&path, &output) {
-            eprintln!("error: failed to write {}: {e}", path.display());
+/// Render every record of a `.dbn` file to `<header>.svg` (or `.json`) in the
+/// `--output` directory, exiting with an error status if any record failed.
+fn render_dbn(cli: &Cli, path: &Path) {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("error: failed to read {}: {e}", path.display());
             process::exit(1);
         }
-    } else {
-        let stdout = std::io::stdout();
-        let mut handle = stdout.lock();
-        if let Err(e) = handle.write_all(output.as_bytes()) {
-            eprintln!("error: write failed: {e}");
-            process::exit(1);
+    };
+    let dir = cli.output.as_deref().unwrap_or(Path::new("."));
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        eprintln!("error: failed to create {}: {e}", dir.display());
+        process::exit(1);
+    }
+    let ext = match cli.format {
+        Format::Svg => "svg",
+        Format::Json => "json",
+    };
+
+    let mut failed = false;
+    let mut used = HashSet::new();
+    for (k, record) in parse_dbn(&text).iter().enumerate() {
+        let name = output_name(&record.header, k, &mut used);
+        let out = dir.join(format!("{name}.{ext}"));
+        let result = render(cli, &record.structure, record.sequence.as_deref())
+            .and_then(|output| std::fs::write(&out, output).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("error: {}: {e}", record.header);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Output file stem for the `k`-th record: its sanitized header, or
+/// `structure_<k+1>` if nothing is left. Headers that sanitize to a name already
+/// in `used` get `_2`, `_3`, … suffixes.
+fn output_name(header: &str, k: usize, used: &mut HashSet<String>) -> String {
+    let name = match sanitize_filename(header) {
+        name if name.is_empty() => format!("structure_{}", k + 1),
+        name => name,
+    };
+    let mut unique = name.clone();
+    for n in 2.. {
+        if used.insert(unique.clone()) {
+            break;
+        }
+        unique = format!("{name}_{n}");
+    }
+    unique
+}
+
+/// One `>header` entry of a `.dbn` file.
+struct DbnRecord {
+    header: String,
+    sequence: Option<String>,
+    structure: String,
+}
+
+/// Split `.dbn` text into records: a `>header` line, then an optional sequence
+/// line and a structure line, with any trailing free energy removed. Blank lines
+/// are skipped; records without a structure are dropped.
+fn parse_dbn(text: &str) -> Vec<DbnRecord> {
+    let mut records = Vec::new();
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .peekable();
+    while let Some(line) = lines.next() {
+        let Some(header) = line.strip_prefix('>') else {
+            continue;
+        };
+        let mut body = Vec::new();
+        while let Some(l) = lines.next_if(|l| !l.starts_with('>')) {
+            body.push(l);
         }
+        let (sequence, structure) = match body[..] {
+            [structure] => (None, structure),
+            [sequence, structure, ..] => (Some(sequence.to_string()), structure),
+            [] => continue,
+        };
+        records.push(DbnRecord {
+            header: header.trim().to_string(),
+            sequence,
+            structure: rnadraw_core::strip_energy(structure).to_string(),
+        });
+    }
+    records
+}
+
+/// Header reduced to a safe file name: its first word, with anything but ASCII
+/// letters, digits, `-`, `_` and `.` replaced by `_`.
+fn sanitize_filename(header: &str) -> String {
+    let word = header.split_whitespace().next().unwrap_or_default();
+    let name: String = word
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.trim_start_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::parse_from(std::iter::once("rnadraw").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_render_without_color_mode() {
+        let svg = render(&cli(&["-s", "((..))"]), "((..))", None).unwrap();
+        let fills: Vec<&str> = svg
+            .split("<circle")
+            .skip(1)
+            .map(|c| {
+                let v = &c[c.find(r#"fill=""#).unwrap() + 6..];
+                &v[..v.find('"').unwrap()]
+            })
+            .collect();
+        assert_eq!(fills.len(), 6);
+        assert!(fills.iter().all(|&f| f == fills[0]), "{fills:?}");
+        assert!(!svg.contains("<linearGradient"));
+    }
+
+    #[test]
+    fn test_parse_dbn() {
+        let text = "\
+>only header
+>hairpin one
+
+GGGAAACCC
+(((...))) (-3.40)
+
+>bare
+((..))
+>trailing
+";
+        let records = parse_dbn(text);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].header, "hairpin one");
+        assert_eq!(records[0].sequence.as_deref(), Some("GGGAAACCC"));
+        assert_eq!(records[0].structure, "(((...)))");
+        assert_eq!(records[1].header, "bare");
+        assert_eq!(records[1].sequence, None);
+        assert_eq!(records[1].structure, "((..))");
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("tRNA-Phe yeast"), "tRNA-Phe");
+        assert_eq!(sanitize_filename("a/b:c"), "a_b_c");
+        assert_eq!(sanitize_filename("../etc"), "_etc");
+        assert_eq!(sanitize_filename("   "), "");
+    }
+
+    #[test]
+    fn test_output_name() {
+        let mut used = HashSet::new();
+        assert_eq!(output_name("seq one", 0, &mut used), "seq");
+        assert_eq!(output_name("seq two", 1, &mut used), "seq_2");
+        assert_eq!(output_name("seq", 2, &mut used), "seq_3");
+        assert_eq!(output_name("", 3, &mut used), "structure_4");
+        assert_eq!(output_name("...", 4, &mut used), "structure_5");
+        // A real header matching an earlier fallback name is suffixed too
+        assert_eq!(output_name("structure_4", 5, &mut used), "structure_4_2");
     }
 }
@@ -1,8 +1,11 @@
+use crate::loops::loop_kind;
 use crate::types::*;
 use std::f64::consts::PI;
+use std::ops::Range;
 
 const NICK_WEIGHT: f64 = 1.38;
 const HALF_PAIR: f64 = 0.5;
+const STRAND_ROW_SPACING: f64 = 2.0;
 const STEM_RADIUS: f64 = 0.6;
 const TWO_PI: f64 = 2.0 * PI;
 
@@ -11,7 +14,11 @@ const EXT_RADIUS: f64 = 0.6765120519;
 const EXT_PAIR_ANGLE: f64 = 1.663422387158712;
 
 /// Calculate loop geometry and base coordinates.
-pub fn calculate(loop_infos: &[LoopInfo], pt: &PairTable) -> (Vec<Loop>, Vec<Base>) {
+pub(crate) fn calculate(
+    loop_infos: &[LoopInfo],
+    pt: &PairTable,
+    opts: &LayoutOptions,
+) -> (Vec<Loop>, Vec<Base>) {
     let n = pt.n_bases;
     if n == 0 || loop_infos.is_empty() {
         return (vec![], vec![]);
@@ -19,15 +26,31 @@ pub fn calculate(loop_infos: &[LoopInfo], pt: &PairTable) -> (Vec<Loop>, Vec<Bas
 
     let n_loops = loop_infos.len();
     let mut loops: Vec<Loop> = Vec::with_capacity(n_loops);
+    // Arc step across the empty side of straightened bulges (see LayoutOptions::straight_bulges)
+    let mut gaps: Vec<Option<f64>> = vec![None; n_loops];
+    // Aspect ratio of loops drawn as ellipses (see LayoutOptions::elliptical_loops)
+    let mut aspects: Vec<Option<f64>> = vec![None; n_loops];
+    let aspect = opts.elliptical_loops;
+    let squash = aspect.is_finite() && aspect > 0.0 && aspect != 1.0;
 
     // Step 1: Calculate radius/height/pair_angle/arc_angle per loop
-    for info in loop_infos {
+    for (li, info) in loop_infos.iter().enumerate() {
         let n_pairs = info.child_pairs.len() + if info.parent_pair.is_some() { 1 } else { 0 };
         let n_unpaired = info.unpaired_bases.len();
         let n_nicks = info.nicks_in_loop.len();
+        let kind = loop_kind(info);
 
         let (radius, pair_angle, arc_angle) =
-            if info.parent_pair.is_none() && n_unpaired == 0 && n_pairs <= 1 {
+            if opts.straight_bulges && kind == LoopKind::Bulge && n_nicks == 0 {
+                // Straight bulge: child pair diametrically opposite the parent pair.
+                // The bulge side holds n_unpaired + 1 unit arcs over π - pa; the empty
+                // side is a single step of the same total angle.
+                let steps = (n_unpaired + 1) as f64;
+                let r = newton_raphson_radius(2.0, 2.0 * steps, opts.min_radius);
+                let pa = 2.0 * (HALF_PAIR / r).asin();
+                gaps[li] = Some(PI - pa);
+                (r, pa, (PI - pa) / steps)
+            } else if info.parent_pair.is_none() && n_unpaired == 0 && n_pairs <= 1 {
                 // External loop with no unpaired bases, single pair: fixed radius
                 // (EXT_RADIUS is the NR solution for n_pairs=1, eff=1.38)
                 let eff = effective_arcs(n_pairs, n_unpaired, n_nicks);
@@ -41,10 +64,16 @@ pub fn calculate(loop_infos: &[LoopInfo], pt: &PairTable) -> (Vec<Loop>, Vec<Bas
                 // Stem: exactly 2 pairs, no unpaired bases.
                 // Covers: internal stems (parent+child, with or without nicks),
                 // 2-pair external loops. Nicks don't affect stem geometry.
-                let pa = 2.0 * (HALF_PAIR / STEM_RADIUS).asin();
+                let r = match (opts.stem_compaction, info.parent_pair) {
+                    (Some(keep), Some(parent)) => {
+                        compacted_stack_radius(pt, parent, keep).unwrap_or(STEM_RADIUS)
+                    }
+                    _ => STEM_RADIUS,
+                };
+                let pa = 2.0 * (HALF_PAIR / r).asin();
                 let eff = effective_arcs(n_pairs, n_unpaired, n_nicks);
                 let aa = (TWO_PI - (n_pairs as f64) * pa) / eff;
-                (STEM_RADIUS, pa, aa)
+                (r, pa, aa)
             } else if n_unpaired == 0 && info.child_pairs.is_empty() {
                 // Empty hairpin (with or without nicks): parent pair only, no children, no unpaired
                 // Uses external loop radius regardless of nicks
@@ -53,8 +82,21 @@ pub fn calculate(loop_infos: &[LoopInfo], pt: &PairTable) -> (Vec<Loop>, Vec<Bas
                 (EXT_RADIUS, EXT_PAIR_ANGLE, aa)
             } else {
                 // Complex loop: Newton-Raphson
+                // loop_spacing adds weight per unpaired base when solving the radius;
+                // arc_angle still divides by the unweighted count so the circle closes.
                 let eff = effective_arcs(n_pairs, n_unpaired, n_nicks);
-                let r = newton_raphson_radius(n_pairs as f64, eff);
+                let nr_eff = eff + (n_unpaired as f64) * (opts.loop_spacing - 1.0);
+                let mut r = newton_raphson_radius(n_pairs as f64, nr_eff, opts.min_radius);
+                if let Some(max) = opts.max_loop_radius {
+                    r = r.min(max.max(min_closing_radius(n_pairs)));
+                }
+                if let Some(min) = opts.min_base_spacing {
+                    r = widen_for_spacing(r, n_pairs as f64, eff, min);
+                }
+                // Interior loops and bulges would widen into the helices beside them
+                if squash && matches!(kind, LoopKind::Hairpin | LoopKind::Multi) {
+                    aspects[li] = Some(aspect);
+                }
                 let pa = 2.0 * (HALF_PAIR / r).asin();
                 let aa = if eff > 0.0 {
                     (TWO_PI - (n_pairs as f64) * pa) / eff
@@ -68,7 +110,9 @@ pub fn calculate(loop_infos: &[LoopInfo], pt: &PairTable) -> (Vec<Loop>, Vec<Bas
 
         loops.push(Loop {
             arc_angle,
+            ellipse: None,
             height,
+            kind,
             pair_angle,
             pairs: vec![],
             radius,
@@ -78,11 +122,21 @@ pub fn calculate(loop_infos: &[LoopInfo], pt: &PairTable) -> (Vec<Loop>, Vec<Bas
     }
 
     // Step 2: BFS — build loop pairs (with correct orientation) and place loops
-    // Returns center angle per loop (needed for base coordinate computation)
-    let centers = bfs_build_and_place(&mut loops, loop_infos, pt);
+    // Returns the angled elements per loop (reused for base coordinate computation)
+    let ctx = PlacementContext {
+        infos: loop_infos,
+        pt,
+        gaps: &gaps,
+        aspects: &aspects,
+        opts,
+    };
+    let placed = bfs_build_and_place(&mut loops, &ctx);
 
     // Step 3: Calculate base coordinates
-    let mut bases = compute_bases(&loops, loop_infos, pt, &centers);
+    let mut bases = compute_bases(&loops, loop_infos, pt, &placed);
+    if opts.straight_tails {
+        straighten_tails(&loops, loop_infos, pt, &mut bases);
+    }
 
     // Step 4: Center all coordinates via bounding box
     // Compute bbox of loop centers + base (x,y) + base (xt,yt),
@@ -92,19 +146,136 @@ pub fn calculate(loop_infos: &[LoopInfo], pt: &PairTable) -> (Vec<Loop>, Vec<Bas
     (loops, bases)
 }
 
+/// Radius of the stack loop closed by `(i, j)` when it lies in the squeezed
+/// middle of a helix longer than `keep` pairs (see `LayoutOptions::stem_compaction`),
+/// None if it keeps the usual `STEM_RADIUS`.
+///
+/// The squeezed steps share the height of one regular step, so the helix keeps
+/// `keep` pairs at full spacing plus one step for the rest.
+fn compacted_stack_radius(pt: &PairTable, (i, j): (usize, usize), keep: usize) -> Option<f64> {
+    let keep = keep.max(2);
+    let paired = |a: usize, b: usize| pt.pairs.get(a) == Some(&b);
+    // Pairs stacked outside (i, j), and inside its child (i + 1, j - 1)
+    let outer = (1..)
+        .take_while(|&k| k <= i && paired(i - k, j + k))
+        .count();
+    let inner = (2..)
+        .take_while(|&k| i + k < j.saturating_sub(k) && paired(i + k, j - k))
+        .count();
+    let len = outer + inner + 2;
+    // Step `outer` joins helix pairs `outer` and `outer + 1`
+    let (first, last) = (keep.div_ceil(2), keep / 2);
+    if len <= keep || outer + 1 < first || outer >= len - last {
+        return None;
+    }
+    let height = (STEM_RADIUS * STEM_RADIUS - HALF_PAIR * HALF_PAIR).sqrt();
+    let h = height / (len - keep + 1) as f64;
+    Some((h * h + HALF_PAIR * HALF_PAIR).sqrt())
+}
+
 /// effective_arcs = n_pairs + n_unpaired + n_nicks * 0.38
 /// (each nick replaces one regular arc with a 1.38× arc)
 fn effective_arcs(n_pairs: usize, n_unpaired: usize, n_nicks: usize) -> f64 {
     (n_pairs + n_unpaired) as f64 + (n_nicks as f64) * (NICK_WEIGHT - 1.0)
 }
 
+/// Smallest radius at which `n_pairs` unit pair bonds fit around a circle.
+fn min_closing_radius(n_pairs: usize) -> f64 {
+    if n_pairs < 2 {
+        HALF_PAIR
+    } else {
+        HALF_PAIR / (PI / n_pairs as f64).sin()
+    }
+}
+
+/// Smallest radius ≥ `r` at which the unpaired steps of a loop with `np` unit pair
+/// bonds and `eff` effective arcs are chords of at least `min`.
+fn widen_for_spacing(r: f64, np: f64, eff: f64, min: f64) -> f64 {
+    if eff <= 0.0 {
+        return r;
+    }
+    let step = |r: f64| {
+        let pa = 2.0 * (HALF_PAIR / r).asin();
+        2.0 * r * ((TWO_PI - np * pa) / eff / 2.0).sin()
+    };
+    if step(r) >= min {
+        return r;
+    }
+    let (mut lo, mut hi) = (r, 2.0 * r);
+    for _ in 0..64 {
+        if step(hi) >= min {
+            break;
+        }
+        lo = hi;
+        hi *= 2.0;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (lo + hi);
+        if step(mid) >= min {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    hi
+}
+
 /// Newton-Raphson: solve n_p * 2*asin(0.5/r) + eff/r = 2π
-fn newton_raphson_radius(np: f64, eff: f64) -> f64 {
+///
+/// If the iteration fails to converge (residual still large, or non-finite), the
+/// root is recovered by bisection so the loop circle always closes. The result
+/// never drops below `min_radius` (nor just above `HALF_PAIR`); a floored loop
+/// leaves a gap that `arc_angle` spreads over its arcs.
+fn newton_raphson_radius(np: f64, eff: f64, min_radius: f64) -> f64 {
+    let floor = min_radius.max(HALF_PAIR + 1e-10);
+    // The residual decreases in r, so the root lies below a floor that already closes
+    if min_radius > HALF_PAIR && closure_residual(np, eff, floor) <= 0.0 {
+        return floor;
+    }
+    let r = newton_raphson_iterate(np, eff, floor);
+    let f = closure_residual(np, eff, r);
+    let r = if r.is_finite() && f.abs() < 1e-6 {
+        r
+    } else {
+        bisect_radius(np, eff)
+    };
+    r.max(min_radius)
+}
+
+/// Angle left over after going once around a loop of radius r (zero when it closes).
+fn closure_residual(np: f64, eff: f64, r: f64) -> f64 {
+    np * 2.0 * (HALF_PAIR / r).min(1.0).asin() + eff / r - TWO_PI
+}
+
+/// Bisection fallback for `newton_raphson_radius`.
+///
+/// The residual decreases monotonically in r. Since asin(x) ≤ x·π/2 on [0, 1], it is
+/// non-positive at r = (n_p·π/2 + eff) / 2π, which bounds the root from above. When
+/// even r = 0.5 leaves a gap (tiny loops), the smallest valid radius is returned.
+fn bisect_radius(np: f64, eff: f64) -> f64 {
+    let mut lo = HALF_PAIR;
+    let mut hi = ((np * PI / 2.0 + eff) / TWO_PI).max(HALF_PAIR);
+    if closure_residual(np, eff, lo) <= 0.0 {
+        return lo;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (lo + hi);
+        if closure_residual(np, eff, mid) > 0.0 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+fn newton_raphson_iterate(np: f64, eff: f64, floor: f64) -> f64 {
     // Initial guess
     let mut r = (np * 1.0 + eff) / TWO_PI;
     if r < HALF_PAIR + 0.01 {
         r = HALF_PAIR + 0.01;
     }
+    r = r.max(floor);
 
     for _ in 0..30 {
         let s = HALF_PAIR / r;
@@ -120,8 +291,8 @@ fn newton_raphson_radius(np: f64, eff: f64) -> f64 {
             break;
         }
         r -= f / df;
-        if r < HALF_PAIR + 1e-10 {
-            r = HALF_PAIR + 1e-10;
+        if r < floor {
+            r = floor;
         }
     }
     r
@@ -137,6 +308,14 @@ enum Elem {
     Nick,
 }
 
+/// A loop's circle elements with their assigned angles, computed once in
+/// `build_loop_pairs` and reused by `compute_bases`.
+#[derive(Debug, Clone, Default)]
+struct PlacedElems {
+    elements: Vec<Elem>,
+    angles: Vec<f64>,
+}
+
 /// Collect elements on a loop's circle in CW traversal order.
 /// Nick markers are inserted between elements based on sequence adjacency.
 fn collect_elements(info: &LoopInfo, pt: &PairTable) -> Vec<Elem> {
@@ -239,23 +418,33 @@ fn collect_elements(info: &LoopInfo, pt: &PairTable) -> Vec<Elem> {
     result
 }
 
+/// Per-loop inputs of `bfs_build_and_place` and `build_loop_pairs`.
+struct PlacementContext<'a> {
+    infos: &'a [LoopInfo],
+    pt: &'a PairTable,
+    /// Arc step across the empty side of straightened bulges
+    gaps: &'a [Option<f64>],
+    /// Aspect ratio of loops drawn as ellipses
+    aspects: &'a [Option<f64>],
+    opts: &'a LayoutOptions,
+}
+
 /// BFS: build loop pairs with correct orientation, then place loop centers.
 ///
-/// For the root loop (external), center = π/2.
+/// For the root loop (external), center = `opts.root_angle` (π/2 by default).
 /// For each child loop visited via BFS, center = incoming_angle + π,
 /// where incoming_angle is the pair angle in the parent loop.
 /// This ensures multiloop branches are correctly rotated.
-fn bfs_build_and_place(loops: &mut [Loop], infos: &[LoopInfo], pt: &PairTable) -> Vec<f64> {
+fn bfs_build_and_place(loops: &mut [Loop], ctx: &PlacementContext) -> Vec<PlacedElems> {
     let n = loops.len();
     if n == 0 {
         return vec![];
     }
 
-    let mut centers = vec![0.0f64; n];
+    let mut placed = vec![PlacedElems::default(); n];
 
-    // Build pairs for loop 0 (external) with center = π/2
-    centers[0] = PI / 2.0;
-    build_loop_pairs(loops, infos, pt, 0, centers[0]);
+    // Build pairs for loop 0 (external) with center = root_angle (π/2 by default)
+    placed[0] = build_loop_pairs(loops, ctx, 0, ctx.opts.root_angle);
     loops[0].x = 0.0;
     loops[0].y = 0.0;
 
@@ -275,14 +464,25 @@ fn bfs_build_and_place(loops: &mut [Loop], infos: &[LoopInfo], pt: &PairTable) -
 
             // The child loop's parent pair should point back towards us.
             let child_center = lp.angle + PI;
-            centers[ni] = child_center;
 
             // Build pairs for child loop with correct orientation
-            build_loop_pairs(loops, infos, pt, ni, child_center);
+            placed[ni] = build_loop_pairs(loops, ctx, ni, child_center);
 
             // Place child: pair midpoint in parent loop
-            let mx = loops[li].x + loops[li].height * lp.angle.cos();
-            let my = loops[li].y + loops[li].height * lp.angle.sin();
+            let (mx, my) = if loops[li].ellipse.is_some() {
+                let (PlacedElems { elements, angles }, l) = (&placed[li], &loops[li]);
+                let at = |b: usize| {
+                    let k = elements.iter().position(|e| elem_base(e) == Some(b));
+                    l.point(angles[k.unwrap_or(0)])
+                };
+                let ((x1, y1), (x2, y2)) = (at(lp.first), at(lp.last));
+                ((x1 + x2) / 2.0, (y1 + y2) / 2.0)
+            } else {
+                (
+                    loops[li].x + loops[li].height * lp.angle.cos(),
+                    loops[li].y + loops[li].height * lp.angle.sin(),
+                )
+            };
 
             // Find angle of parent pair in child loop
             let ni_angle = loops[ni]
@@ -300,40 +500,49 @@ fn bfs_build_and_place(loops: &mut [Loop], infos: &[LoopInfo], pt: &PairTable) -
         }
     }
 
-    centers
+    placed
 }
 
 /// Build LoopPair entries for a single loop with the given center angle.
+///
+/// With an aspect set for the loop, the elements are then moved onto an ellipse
+/// (see `squash_loop`).
 fn build_loop_pairs(
     loops: &mut [Loop],
-    infos: &[LoopInfo],
-    pt: &PairTable,
+    ctx: &PlacementContext,
     li: usize,
     center: f64,
-) {
+) -> PlacedElems {
+    let (infos, pt, opts) = (ctx.infos, ctx.pt, ctx.opts);
     loops[li].pairs.clear();
     let info = &infos[li];
     let elements = collect_elements(info, pt);
     if elements.is_empty() {
-        return;
+        return PlacedElems::default();
     }
 
     let r = loops[li].radius;
     let half_pa = (HALF_PAIR / r).asin();
-    let pair_a = 2.0 * half_pa;
     let arc_a = loops[li].arc_angle;
-    let nick_a = arc_a * NICK_WEIGHT;
     let is_external = info.parent_pair.is_none();
-
-    let angles = assign_angles(
-        &elements,
-        half_pa,
-        pair_a,
+    let steps = LoopSteps {
+        pair_a: 2.0 * half_pa,
         arc_a,
-        nick_a,
+        nick_a: arc_a * NICK_WEIGHT,
+        gap_a: ctx.gaps[li],
         is_external,
-        center,
-    );
+    };
+
+    let mut angles = assign_angles(&elements, &steps, center);
+    if opts.unpaired_bias != 0.0 {
+        bias_unpaired_runs(&elements, &mut angles, opts.unpaired_bias);
+    }
+    for &(a, b) in &opts.coaxial {
+        stack_coaxial(&elements, &mut angles, is_external, pt, a, b);
+    }
+    if let Some(k) = ctx.aspects[li] {
+        squash_loop(&mut loops[li], &elements, &mut angles, center, k);
+    }
 
     // Extract pairs: pair center = PairLast_angle + half_pa
     for (i, elem) in elements.iter().enumerate() {
@@ -342,7 +551,19 @@ fn build_loop_pairs(
                 .iter()
                 .position(|e| matches!(e, Elem::PairLast(b, _, _) if *b == *last))
                 .unwrap_or(i);
-            let pair_center = angles[last_idx] + half_pa;
+            let pair_center = match &loops[li].ellipse {
+                None => angles[last_idx] + half_pa,
+                Some(_) => {
+                    // Outward normal of the bond, which is no longer radial
+                    let l = &loops[li];
+                    let (x1, y1) = l.point(angles[i]);
+                    let (x2, y2) = l.point(angles[last_idx]);
+                    let (mx, my) = ((x1 + x2) / 2.0 - l.x, (y1 + y2) / 2.0 - l.y);
+                    let (nx, ny) = (y2 - y1, x1 - x2);
+                    let sign = if nx * mx + ny * my < 0.0 { -1.0 } else { 1.0 };
+                    (sign * ny).atan2(sign * nx)
+                }
+            };
             let neighbor = find_neighbor_loop(infos, li, *first, *last);
             loops[li].pairs.push(LoopPair {
                 angle: pair_center,
@@ -352,6 +573,147 @@ fn build_loop_pairs(
             });
         }
     }
+
+    PlacedElems { elements, angles }
+}
+
+/// Base index of a circle element, None for nick markers.
+fn elem_base(e: &Elem) -> Option<usize> {
+    match e {
+        Elem::PairFirst(b, _, _) | Elem::PairLast(b, _, _) | Elem::Unpaired(b) => Some(*b),
+        Elem::Nick => None,
+    }
+}
+
+/// Move an internal loop's elements from its circle onto an ellipse whose
+/// semi-axis towards the parent pair (at angle `center`) is `aspect` times the
+/// one across it.
+///
+/// Consecutive bases keep the chord length they had on the circle, so pair bonds
+/// stay unit length, and the parent pair stays symmetric about the `center` axis.
+/// The ellipse size is solved by bisection so the walk from one parent base round
+/// to the other closes. `angles` become parametric angles on the ellipse, and the
+/// loop's `radius`/`height`/`ellipse` are updated to match. Leaves the loop a
+/// circle if no ellipse fits or its minor semi-axis would drop below `HALF_PAIR`.
+fn squash_loop(l: &mut Loop, elements: &[Elem], angles: &mut [f64], center: f64, aspect: f64) {
+    let n = elements.len();
+    if n < 3 {
+        return;
+    }
+    // Chord from the previous base for each element (0 for nick markers)
+    let mut chords = vec![0.0; n];
+    let mut prev = 0;
+    for i in 1..n {
+        if elem_base(&elements[i]).is_some() {
+            chords[i] = 2.0 * l.radius * ((angles[i] - angles[prev]) / 2.0).sin().abs();
+            prev = i;
+        }
+    }
+
+    // Walk in the loop's local frame (x towards the parent pair); returns the
+    // parametric angle of each element, or None if a chord doesn't fit
+    let walk = |b: f64| -> Option<Vec<f64>> {
+        let a = aspect * b;
+        let at = |s: f64| (a * s.cos(), b * s.sin());
+        let mut s = (HALF_PAIR / b).asin();
+        let mut out = Vec::with_capacity(n);
+        out.push(s);
+        for &d in &chords[1..] {
+            if d > 0.0 {
+                s = next_on_ellipse(&at, s, d, (a * s.sin()).hypot(b * s.cos()))?;
+            }
+            out.push(s);
+        }
+        Some(out)
+    };
+    // Signed gap left at the closing pair: positive once the ellipse is big enough
+    let gap = |b: f64| match walk(b) {
+        Some(s) => TWO_PI - (HALF_PAIR / b).asin() - s[n - 1],
+        None => -1.0,
+    };
+
+    let mut lo = HALF_PAIR;
+    let mut hi = l.radius.max(HALF_PAIR / aspect).max(HALF_PAIR);
+    for _ in 0..64 {
+        if gap(hi) >= 0.0 {
+            break;
+        }
+        lo = hi;
+        hi *= 2.0;
+    }
+    if gap(hi) < 0.0 {
+        return;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (lo + hi);
+        if gap(mid) >= 0.0 {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    let b = hi;
+    // Too small to flatten: bases across the loop would come closer than a bond
+    if (aspect * b).min(b) < HALF_PAIR {
+        return;
+    }
+    let Some(mut s) = walk(b) else {
+        return;
+    };
+    // Land the closing parent base exactly opposite the first
+    let s0 = (HALF_PAIR / b).asin();
+    s[n - 1] = TWO_PI - s0;
+    for (angle, si) in angles.iter_mut().zip(&s) {
+        *angle = center + si - TWO_PI;
+    }
+    l.radius = aspect * b;
+    l.height = l.radius * s0.cos();
+    l.ellipse = Some(Ellipse {
+        rotation: center,
+        ry: b,
+    });
+}
+
+/// Smallest parametric angle past `s` at chord distance `d` from `at(s)`, stepping
+/// by about `d / speed` before bisecting; None if nothing within a turn is that far.
+fn next_on_ellipse(at: &impl Fn(f64) -> (f64, f64), s: f64, d: f64, speed: f64) -> Option<f64> {
+    let (x0, y0) = at(s);
+    let dist = |t: f64| {
+        let (x, y) = at(t);
+        (x - x0).hypot(y - y0)
+    };
+    let step = (0.5 * d / speed.max(1e-9)).min(0.1);
+    let (mut lo, mut hi) = (s, s + step);
+    while dist(hi) < d {
+        if hi - s > TWO_PI {
+            return None;
+        }
+        lo = hi;
+        hi += step;
+    }
+    for _ in 0..60 {
+        let mid = 0.5 * (lo + hi);
+        if dist(mid) < d {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Some(hi)
+}
+
+/// Angular step sizes around one loop, see `step_between`.
+#[derive(Clone, Copy)]
+struct LoopSteps {
+    /// Across a pair bond
+    pair_a: f64,
+    /// Per unpaired step
+    arc_a: f64,
+    /// After a strand break
+    nick_a: f64,
+    /// Between two different pairs, replacing `arc_a` (straightened bulges)
+    gap_a: Option<f64>,
+    is_external: bool,
 }
 
 /// Assign angles to loop elements.
@@ -362,15 +724,11 @@ fn build_loop_pairs(
 /// `center` is the angle of the parent pair (for internal) or the first
 /// child pair direction (for external). For internal loops in multiloop
 /// branches, this is rotated to match the actual incoming direction.
-fn assign_angles(
-    elements: &[Elem],
-    half_pa: f64,
-    pair_a: f64,
-    arc_a: f64,
-    nick_a: f64,
-    is_external: bool,
-    center: f64,
-) -> Vec<f64> {
+///
+/// `steps.gap_a`, if set, replaces the arc step between elements of two different pairs.
+fn assign_angles(elements: &[Elem], steps: &LoopSteps, center: f64) -> Vec<f64> {
+    let is_external = steps.is_external;
+    let half_pa = steps.pair_a / 2.0;
     let n = elements.len();
     let mut angles = vec![0.0f64; n];
 
@@ -384,14 +742,7 @@ fn assign_angles(
     let mut cur = first_angle;
     for i in 0..n {
         if i > 0 {
-            let step = step_between(
-                &elements[i - 1],
-                &elements[i],
-                pair_a,
-                arc_a,
-                nick_a,
-                is_external,
-            );
+            let step = step_between(&elements[i - 1], &elements[i], steps);
             if is_external {
                 cur -= step;
             } else {
@@ -403,20 +754,142 @@ fn assign_angles(
     angles
 }
 
+/// Redistribute the steps along each run of unpaired bases between two other
+/// elements, keeping the run's total angle (see `LayoutOptions::unpaired_bias`).
+///
+/// Step s of a run of m steps is weighted by `1 + bias * (4 * min(u, 1 - u) - 1)`
+/// with `u = (s + 0.5) / m`, so positive `bias` shortens the steps next to the
+/// bounding elements and lengthens those in the middle.
+fn bias_unpaired_runs(elements: &[Elem], angles: &mut [f64], bias: f64) {
+    let bias = bias.clamp(-0.9, 0.9);
+    let n = elements.len();
+    let mut k = 1;
+    while k < n {
+        if !matches!(elements[k], Elem::Unpaired(_)) {
+            k += 1;
+            continue;
+        }
+        let start = k - 1;
+        while k < n && matches!(elements[k], Elem::Unpaired(_)) {
+            k += 1;
+        }
+        // Runs reaching the end of the list have no closing element
+        if k == n {
+            break;
+        }
+        let m = k - start;
+        let steps: Vec<f64> = (start..k).map(|i| angles[i + 1] - angles[i]).collect();
+        let weights: Vec<f64> = (0..m)
+            .map(|s| {
+                let u = (s as f64 + 0.5) / m as f64;
+                1.0 + bias * (4.0 * u.min(1.0 - u) - 1.0)
+            })
+            .collect();
+        let total: f64 = steps.iter().sum();
+        let weighted: f64 = steps.iter().zip(&weights).map(|(d, w)| d * w).sum();
+        if weighted == 0.0 {
+            continue;
+        }
+        // The closing element keeps its angle
+        for (s, (d, w)) in steps.iter().zip(&weights).enumerate().take(m - 1) {
+            angles[start + s + 1] = angles[start + s] + d * w * total / weighted;
+        }
+    }
+}
+
+/// Re-space a loop's elements so the pairs containing bases `a` and `b` sit
+/// diametrically opposite, making their helices collinear.
+///
+/// Step k joins element k to element k+1 (the last wraps to element 0). Pair bonds
+/// and nick markers keep their width; the other steps on each side between the two
+/// pair centers are scaled to fill exactly π. Element 0 keeps its angle, so the
+/// parent pair of an internal loop stays where it was. Leaves the angles untouched
+/// unless both pairs are on this loop and each side has room.
+fn stack_coaxial(
+    elements: &[Elem],
+    angles: &mut [f64],
+    is_external: bool,
+    pt: &PairTable,
+    a: usize,
+    b: usize,
+) {
+    let n = elements.len();
+    let pair_key = |x: usize| match pt.pairs.get(x) {
+        Some(&p) if p != x => Some(x.min(p)),
+        _ => None,
+    };
+    let elem_key = |e: &Elem| match *e {
+        Elem::PairFirst(x, y, _) | Elem::PairLast(x, y, _) => Some(x.min(y)),
+        _ => None,
+    };
+    let (Some(ka), Some(kb)) = (pair_key(a), pair_key(b)) else {
+        return;
+    };
+    let joins_pair = |k: usize| {
+        let key = elem_key(&elements[k]);
+        key.is_some() && key == elem_key(&elements[(k + 1) % n])
+    };
+    let bond = |key| (0..n).find(|&k| joins_pair(k) && elem_key(&elements[k]) == Some(key));
+    let (Some(sa), Some(sb)) = (bond(ka), bond(kb)) else {
+        return;
+    };
+    if sa == sb {
+        return;
+    }
+
+    let dir = if is_external { -1.0 } else { 1.0 };
+    let mut steps: Vec<f64> = (0..n - 1)
+        .map(|k| (angles[k + 1] - angles[k]) * dir)
+        .collect();
+    steps.push(TWO_PI - steps.iter().sum::<f64>());
+    let fixed: Vec<bool> = (0..n).map(|k| steps[k] == 0.0 || joins_pair(k)).collect();
+
+    let half_bonds = 0.5 * (steps[sa] + steps[sb]);
+    let mut scales = Vec::with_capacity(2);
+    for (from, to) in [(sa, sb), (sb, sa)] {
+        let side: Vec<usize> = (1..n)
+            .map(|d| (from + d) % n)
+            .take_while(|&k| k != to)
+            .collect();
+        let (mut fixed_sum, mut flex_sum) = (half_bonds, 0.0);
+        for &k in &side {
+            if fixed[k] {
+                fixed_sum += steps[k];
+            } else {
+                flex_sum += steps[k];
+            }
+        }
+        if fixed_sum >= PI || flex_sum <= 0.0 {
+            return;
+        }
+        scales.push((side, (PI - fixed_sum) / flex_sum));
+    }
+    for (side, factor) in scales {
+        for k in side {
+            if !fixed[k] {
+                steps[k] *= factor;
+            }
+        }
+    }
+    for k in 1..n {
+        angles[k] = angles[k - 1] + dir * steps[k - 1];
+    }
+}
+
 /// Angular step between consecutive elements in the traversal.
 ///
 /// PF→PL of same child pair: always pair_a (entering pair bond).
 /// PL→PF of same child pair: depends on direction.
 ///   - External (CW): arc_a (going the long way around)
 ///   - Internal (CCW): pair_a (going the short way = pair bond)
-fn step_between(
-    prev: &Elem,
-    curr: &Elem,
-    pair_a: f64,
-    arc_a: f64,
-    nick_a: f64,
-    is_external: bool,
-) -> f64 {
+fn step_between(prev: &Elem, curr: &Elem, steps: &LoopSteps) -> f64 {
+    let LoopSteps {
+        pair_a,
+        arc_a,
+        nick_a,
+        gap_a,
+        is_external,
+    } = *steps;
     if matches!(curr, Elem::Nick) {
         return 0.0;
     }
@@ -437,12 +910,24 @@ fn step_between(
     ) {
         return if is_external { arc_a } else { pair_a };
     }
+    // Pair → different pair with nothing in between
+    if let Some(gap) = gap_a {
+        let is_pair = |e: &Elem| matches!(e, Elem::PairFirst(..) | Elem::PairLast(..));
+        if is_pair(prev) && is_pair(curr) {
+            return gap;
+        }
+    }
     arc_a
 }
 
 // ── Base coordinate computation ─────────────────────────────────────
 
-fn compute_bases(loops: &[Loop], infos: &[LoopInfo], pt: &PairTable, centers: &[f64]) -> Vec<Base> {
+fn compute_bases(
+    loops: &[Loop],
+    infos: &[LoopInfo],
+    pt: &PairTable,
+    placed: &[PlacedElems],
+) -> Vec<Base> {
     let n = pt.n_bases;
     let mut bases = vec![
         Base {
@@ -460,28 +945,9 @@ fn compute_bases(loops: &[Loop], infos: &[LoopInfo], pt: &PairTable, centers: &[
         n
     ];
 
-    for (li, info) in infos.iter().enumerate() {
+    for li in 0..infos.len() {
         let lp = &loops[li];
-        let elements = collect_elements(info, pt);
-        if elements.is_empty() {
-            continue;
-        }
-
-        let half_pa = (HALF_PAIR / lp.radius).asin();
-        let pair_a = 2.0 * half_pa;
-        let arc_a = lp.arc_angle;
-        let nick_a = arc_a * NICK_WEIGHT;
-        let is_external = info.parent_pair.is_none();
-
-        let angles = assign_angles(
-            &elements,
-            half_pa,
-            pair_a,
-            arc_a,
-            nick_a,
-            is_external,
-            centers[li],
-        );
+        let PlacedElems { elements, angles } = &placed[li];
 
         for (i, elem) in elements.iter().enumerate() {
             let base_idx = match elem {
@@ -490,8 +956,7 @@ fn compute_bases(loops: &[Loop], infos: &[LoopInfo], pt: &PairTable, centers: &[
             };
 
             let angle = angles[i];
-            let x = lp.x + lp.radius * angle.cos();
-            let y = lp.y + lp.radius * angle.sin();
+            let (x, y) = lp.point(angle);
 
             bases[base_idx].x = x;
             bases[base_idx].y = y;
@@ -521,15 +986,11 @@ fn compute_bases(loops: &[Loop], infos: &[LoopInfo], pt: &PairTable, centers: &[
     }
 
     // Build strand start/end sets from nick positions.
-    // nicks[0] is always 0 (start of first strand).
-    // Each nick value is the base index of the first base in a new strand.
     let mut strand_starts: Vec<bool> = vec![false; n];
     let mut strand_ends: Vec<bool> = vec![false; n];
-    for &nick in &pt.nicks {
-        strand_starts[nick] = true;
-        // The base before the nick is a strand end
-        let prev = if nick == 0 { n - 1 } else { nick - 1 };
-        strand_ends[prev] = true;
+    for (start, end) in strand_ranges(&pt.nicks, n) {
+        strand_starts[start] = true;
+        strand_ends[end - 1] = true;
     }
 
     // Compute xt, yt, lengths
@@ -554,8 +1015,14 @@ fn compute_bases(loops: &[Loop], infos: &[LoopInfo], pt: &PairTable, centers: &[
             let li = bases[i].loop1;
             let lp = &loops[li];
             let angle = bases[i].angle1;
-            bases[i].xt = lp.x + (lp.radius + HALF_PAIR) * angle.cos();
-            bases[i].yt = lp.y + (lp.radius + HALF_PAIR) * angle.sin();
+            if let Some(e) = &lp.ellipse {
+                let ((x, y), (nx, ny)) = (lp.point(angle), e.normal(lp.radius, angle));
+                bases[i].xt = x + HALF_PAIR * nx;
+                bases[i].yt = y + HALF_PAIR * ny;
+            } else {
+                bases[i].xt = lp.x + (lp.radius + HALF_PAIR) * angle.cos();
+                bases[i].yt = lp.y + (lp.radius + HALF_PAIR) * angle.sin();
+            }
             bases[i].length1 = if strand_starts[i] { 0.69 } else { 0.5 };
             bases[i].length2 = if strand_ends[i] { 0.69 } else { 0.5 };
         }
@@ -564,6 +1031,176 @@ fn compute_bases(loops: &[Loop], infos: &[LoopInfo], pt: &PairTable, centers: &[
     bases
 }
 
+// ── Straight tails ──────────────────────────────────────────────────
+
+/// Base spacing along a straightened tail, about the arc length per base on loops.
+const TAIL_SPACING: f64 = 1.0;
+
+/// Unpaired 5′ tail (before the first pair) and 3′ tail (after the last pair).
+///
+/// A tail interrupted by a strand break is not a tail and comes back empty.
+pub(crate) fn terminal_tails(pt: &PairTable) -> (Range<usize>, Range<usize>) {
+    let n = pt.n_bases;
+    let is_paired = |i: &usize| pt.pairs[*i] != *i;
+    let (Some(first), Some(last)) = ((0..n).find(is_paired), (0..n).rfind(is_paired)) else {
+        return (0..0, 0..0);
+    };
+    let unbroken = |r: &Range<usize>| !pt.nicks.iter().any(|&k| r.start < k && k <= r.end);
+    let lead = 0..first;
+    let trail = last + 1..n;
+    (
+        if unbroken(&lead) { lead } else { 0..0 },
+        if unbroken(&(last..n - 1)) {
+            trail
+        } else {
+            0..0
+        },
+    )
+}
+
+/// Move the terminal tails onto straight lines continuing the terminal pair bonds
+/// outward, with labels on the side away from the helix.
+fn straighten_tails(loops: &[Loop], infos: &[LoopInfo], pt: &PairTable, bases: &mut [Base]) {
+    let (lead, trail) = terminal_tails(pt);
+    // (anchor paired base, tail bases ordered away from it)
+    let tails: [(usize, Vec<usize>); 2] = [
+        (lead.end, lead.clone().rev().collect()),
+        (trail.start.wrapping_sub(1), trail.clone().collect()),
+    ];
+    for (anchor, tail) in tails {
+        if tail.is_empty() {
+            continue;
+        }
+        let partner = pt.pairs[anchor];
+        let (a, p) = (&bases[anchor], &bases[partner]);
+        let len = (a.x - p.x).hypot(a.y - p.y);
+        let (dx, dy) = ((a.x - p.x) / len, (a.y - p.y) / len);
+
+        // Labels go to the side facing away from the loop the pair closes
+        let closed = (anchor.min(partner), anchor.max(partner));
+        let inner = infos
+            .iter()
+            .position(|info| info.parent_pair == Some(closed))
+            .unwrap_or(0);
+        let (mx, my) = ((a.x + p.x) / 2.0, (a.y + p.y) / 2.0);
+        let side = if (loops[inner].x - mx) * -dy + (loops[inner].y - my) * dx > 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        let (nx, ny) = (-dy * side, dx * side);
+
+        let (ax, ay) = (a.x, a.y);
+        for (k, &b) in tail.iter().enumerate() {
+            let d = TAIL_SPACING * (k + 1) as f64;
+            let (x, y) = (ax + d * dx, ay + d * dy);
+            let base = &mut bases[b];
+            base.x = x;
+            base.y = y;
+            base.xt = x + HALF_PAIR * nx;
+            base.yt = y + HALF_PAIR * ny;
+            base.angle1 = ny.atan2(nx);
+            base.angle2 = base.angle1;
+        }
+    }
+}
+
+// ── Unstructured layout ─────────────────────────────────────────────
+
+/// Lay out a structure without pairs as a horizontal line of bases spaced
+/// `HALF_PAIR` apart, labels above. With several strands each gets its own
+/// centered row, 5′ strand on top, rows `STRAND_ROW_SPACING` apart. A single
+/// empty external loop is returned so `loop1`/`loop2` stay valid indices.
+pub(crate) fn calculate_linear(pt: &PairTable) -> (Vec<Loop>, Vec<Base>) {
+    let n = pt.n_bases;
+    if n == 0 {
+        return (vec![], vec![]);
+    }
+    let external = Loop {
+        arc_angle: 0.0,
+        ellipse: None,
+        height: 0.0,
+        kind: LoopKind::External,
+        pair_angle: 0.0,
+        pairs: vec![],
+        radius: 0.0,
+        x: 0.0,
+        y: 0.0,
+    };
+
+    let strands = strand_ranges(&pt.nicks, n);
+    let y0 = STRAND_ROW_SPACING * strands.len().saturating_sub(1) as f64 / 2.0;
+    let mut bases = Vec::with_capacity(n);
+    for (row, &(start, end)) in strands.iter().enumerate() {
+        let y = y0 - STRAND_ROW_SPACING * row as f64;
+        let x0 = -HALF_PAIR * (end - start - 1) as f64 / 2.0;
+        bases.extend((start..end).map(|i| {
+            let x = x0 + HALF_PAIR * (i - start) as f64;
+            Base {
+                angle1: PI / 2.0,
+                angle2: PI / 2.0,
+                length1: if i == start { 0.69 } else { 0.5 },
+                length2: if i + 1 == end { 0.69 } else { 0.5 },
+                loop1: 0,
+                loop2: 0,
+                x,
+                xt: x,
+                y,
+                yt: y + HALF_PAIR,
+            }
+        }));
+    }
+    (vec![external], bases)
+}
+
+// ── Metrics ─────────────────────────────────────────────────────────
+
+/// Radius, angles and base spacing of loop `li` in a finished layout.
+///
+/// `min_base_spacing` is measured on the placed bases of the loop (those with
+/// `loop1` or `loop2` equal to `li`), taking the closest pair of neighbors in
+/// angular order around the loop center, so it reflects options such as
+/// `straight_bulges`, `straight_tails` and `elliptical_loops`. It is infinite
+/// when the loop has fewer than two bases. `circumference` follows the ellipse
+/// of elliptical loops (Ramanujan's approximation).
+/// Returns None if `li` is out of range.
+pub fn loop_metrics(result: &DrawResult, li: usize) -> Option<LoopMetrics> {
+    let l = result.layout.loops.get(li)?;
+    let mut placed: Vec<(f64, f64, f64)> = result
+        .layout
+        .bases
+        .iter()
+        .filter(|b| b.loop1 == li || b.loop2 == li)
+        .map(|b| ((b.y - l.y).atan2(b.x - l.x), b.x, b.y))
+        .collect();
+    placed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let dist = |a: &(f64, f64, f64), b: &(f64, f64, f64)| (a.1 - b.1).hypot(a.2 - b.2);
+    let mut min_base_spacing = f64::INFINITY;
+    if placed.len() >= 2 {
+        for w in placed.windows(2) {
+            min_base_spacing = min_base_spacing.min(dist(&w[0], &w[1]));
+        }
+        let wrap = dist(&placed[placed.len() - 1], &placed[0]);
+        min_base_spacing = min_base_spacing.min(wrap);
+    }
+
+    let circumference = match &l.ellipse {
+        None => TWO_PI * l.radius,
+        Some(e) => {
+            let (a, b) = (l.radius, e.ry);
+            PI * (3.0 * (a + b) - ((3.0 * a + b) * (a + 3.0 * b)).sqrt())
+        }
+    };
+    Some(LoopMetrics {
+        radius: l.radius,
+        arc_angle: l.arc_angle,
+        pair_angle: l.pair_angle,
+        circumference,
+        min_base_spacing,
+    })
+}
+
 // ── Bounding box centering (Phase 4) ────────────────────────
 
 fn center_coordinates(loops: &mut [Loop], bases: &mut [Base]) {
@@ -1,74 +1,484 @@
-mod geometry;
+pub mod geometry;
 mod loops;
 mod parser;
 mod segments;
 pub mod svg;
 mod types;
+use serde::Serialize;
 use std::f64::consts::PI;
 
-pub use loops::decompose;
-pub use parser::parse;
+pub use loops::{decompose, loop_kind};
+pub use parser::{from_partners, parse, parse_vienna, parse_with_options, strip_energy};
 pub use types::*;
 
-fn compute_draw_result(input: &str) -> Option<DrawResult> {
-    let pt = parser::parse(input).ok()?;
+fn compute_draw_result(input: &str, opts: &LayoutOptions) -> Option<DrawResult> {
+    try_compute_draw_result(input, opts).ok()
+}
+
+fn try_compute_draw_result(input: &str, opts: &LayoutOptions) -> Result<DrawResult, DrawError> {
+    let pt = parser::parse_typed(input, &ParseOptions::default())?;
+    try_layout_pair_table(&pt, opts)
+}
+
+fn layout_pair_table(pt: &PairTable, opts: &LayoutOptions) -> Option<DrawResult> {
+    try_layout_pair_table(pt, opts).ok()
+}
+
+fn try_layout_pair_table(pt: &PairTable, opts: &LayoutOptions) -> Result<DrawResult, DrawError> {
     if pt.n_bases == 0 {
-        return None;
+        return Err(DrawError::Empty);
     }
-    let loop_infos = loops::decompose(&pt);
-    if loop_infos.is_empty() {
-        return None;
+    if has_pseudoknot(pt) {
+        return Err(DrawError::Pseudoknot);
     }
-    let (layout_loops, bases) = geometry::calculate(&loop_infos, &pt);
-    let segs = segments::generate(&layout_loops, &bases, &pt, &loop_infos);
-    Some(DrawResult {
+    let loop_infos = loops::decompose(pt);
+    let (layout_loops, bases, segs) = if !loop_infos.is_empty() {
+        let (layout_loops, bases) = geometry::calculate(&loop_infos, pt, opts);
+        let segs = segments::generate(&layout_loops, &bases, pt, &loop_infos, opts);
+        (layout_loops, bases, segs)
+    } else if opts.draw_unstructured || pt.nicks.len() > 1 {
+        // Unpaired strands (e.g. a primer pair) are always drawn, one row each
+        let (layout_loops, bases) = geometry::calculate_linear(pt);
+        let segs = segments::generate_linear(&bases, pt);
+        (layout_loops, bases, segs)
+    } else {
+        return Err(DrawError::NoPairs);
+    };
+    Ok(DrawResult {
         layout: Layout {
             bases,
             loops: layout_loops,
         },
-        nicks: pt.nicks,
-        pairs: pt.pairs,
+        nicks: pt.nicks.clone(),
+        pairs: pt.pairs.clone(),
         segments: segs,
+        stats: opts.stats.then(|| loops::structure_stats(&loop_infos, pt)),
     })
 }
 
+/// Whether any two pairs cross (i < k < j < l), which the loop decomposition
+/// cannot lay out. Out-of-range partners are ignored.
+pub fn has_pseudoknot(pt: &PairTable) -> bool {
+    let mut open: Vec<usize> = Vec::new();
+    for (i, &j) in pt.pairs.iter().enumerate() {
+        if j > i {
+            open.push(j);
+        } else if j < i {
+            // The innermost open pair must be the one closing here
+            if open.pop() != Some(i) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Check the invariants `parse` guarantees: symmetric, non-crossing pairs over
+/// `n_bases` bases and strictly increasing nicks starting at 0.
+fn is_valid_pair_table(pt: &PairTable) -> bool {
+    let n = pt.n_bases;
+    if pt.pairs.len() != n || pt.nicks.first() != Some(&0) {
+        return false;
+    }
+    if pt.nicks.windows(2).any(|w| w[0] >= w[1]) || pt.nicks.iter().any(|&k| k >= n) {
+        return false;
+    }
+    let mut open: Vec<usize> = Vec::new();
+    for (i, &j) in pt.pairs.iter().enumerate() {
+        if j >= n || pt.pairs[j] != i {
+            return false;
+        }
+        if j > i {
+            open.push(i);
+        } else if j < i && open.pop() != Some(j) {
+            return false;
+        }
+    }
+    true
+}
+
 /// Main entry point: takes dot-bracket-plus notation, returns JSON string.
 pub fn draw_structure(input: &str) -> String {
-    compute_draw_result(input)
+    draw_structure_with_options(input, &LayoutOptions::default())
+}
+
+/// Like `draw_structure`, reporting why no layout was produced instead of
+/// returning an empty string.
+pub fn draw_structure_result(input: &str) -> Result<String, DrawError> {
+    let r = try_compute_draw_result(input, &LayoutOptions::default())?;
+    Ok(serde_json::to_string(&r).unwrap_or_default())
+}
+
+/// Like `draw_structure`, with custom layout options.
+pub fn draw_structure_with_options(input: &str, opts: &LayoutOptions) -> String {
+    compute_draw_result(input, opts)
         .and_then(|r| serde_json::to_string(&r).ok())
         .unwrap_or_default()
 }
 
-/// Render dot-bracket-plus notation as SVG.
-pub fn draw_svg(input: &str, seq: Option<&str>, opts: &svg::SvgOptions) -> String {
-    compute_draw_result(input)
+/// Like `draw_structure`, with `y_down` emitting screen coordinates (y grows
+/// downward, as in SVG) so the JSON can be drawn without flipping.
+///
+/// Every y is negated, as are base, pair, and arc angles, so each arc still
+/// runs from `t1` to `t2` about its (flipped) center.
+pub fn draw_structure_oriented(input: &str, y_down: bool) -> String {
+    compute_draw_result(input, &LayoutOptions::default())
         .map(|mut r| {
-            if opts.align_stem {
-                if let Some(angle) = compute_stem_rotation(&r) {
-                    rotate_result(&mut r, angle);
-                }
+            if y_down {
+                flip_y(&mut r);
             }
-            svg::render(&r, seq, opts)
+            r
+        })
+        .and_then(|r| serde_json::to_string(&r).ok())
+        .unwrap_or_default()
+}
+
+/// Like `draw_structure`, with each segment tagged `"type": "line"` or
+/// `"type": "arc"` so consumers need not infer the kind from its fields.
+pub fn draw_structure_tagged(input: &str) -> String {
+    compute_draw_result(input, &LayoutOptions::default())
+        .and_then(|r| {
+            let tagged = TaggedDrawResult {
+                layout: &r.layout,
+                nicks: &r.nicks,
+                pairs: &r.pairs,
+                segments: r
+                    .segments
+                    .iter()
+                    .map(|segs| segs.iter().map(TaggedSegment::from).collect())
+                    .collect(),
+            };
+            serde_json::to_string(&tagged).ok()
         })
         .unwrap_or_default()
 }
 
+/// `DrawResult` with internally tagged segments, for `draw_structure_tagged`.
+#[derive(Serialize)]
+struct TaggedDrawResult<'a> {
+    layout: &'a Layout,
+    nicks: &'a [usize],
+    pairs: &'a [usize],
+    segments: Vec<Vec<TaggedSegment<'a>>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TaggedSegment<'a> {
+    Line(&'a LineSegment),
+    Arc(&'a ArcSegment),
+}
+
+impl<'a> From<&'a Segment> for TaggedSegment<'a> {
+    fn from(seg: &'a Segment) -> Self {
+        match seg {
+            Segment::Line(line) => Self::Line(line),
+            Segment::Arc(arc) => Self::Arc(arc),
+        }
+    }
+}
+
+/// Compute the typed layout for dot-bracket-plus notation.
+pub fn layout(input: &str) -> Option<DrawResult> {
+    layout_with_options(input, &LayoutOptions::default())
+}
+
+/// Like `layout`, with custom layout options.
+pub fn layout_with_options(input: &str, opts: &LayoutOptions) -> Option<DrawResult> {
+    compute_draw_result(input, opts)
+}
+
+/// Per-base pairing state (`true` = paired), indexed like the layout bases
+/// (strand breaks are not bases). None if the input does not parse.
+pub fn pairing_mask(input: &str) -> Option<Vec<bool>> {
+    let pt = parser::parse(input).ok()?;
+    Some(pt.pairs.iter().enumerate().map(|(i, &j)| i != j).collect())
+}
+
+/// Check that `seq` holds only A, C, G, U (case-insensitive) and `+`/`&` strand
+/// breaks, plus T when `allow_t` is set.
+///
+/// On failure, lists each offending character with its 0-based position in `seq`
+/// (strand breaks counted), so callers can report it before coloring by nucleotide.
+pub fn validate_sequence(seq: &str, allow_t: bool) -> Result<(), Vec<(usize, char)>> {
+    let invalid: Vec<(usize, char)> = seq
+        .chars()
+        .enumerate()
+        .filter(|&(_, c)| match c.to_ascii_uppercase() {
+            'A' | 'C' | 'G' | 'U' | '+' | '&' => false,
+            'T' => !allow_t,
+            _ => true,
+        })
+        .collect();
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid)
+    }
+}
+
+/// Count GC, AU, GU, mismatched, and ambiguous pairs of `input` against `seq`.
+///
+/// Case-insensitive, with T treated as U. `+`/`&` strand breaks in the sequence are
+/// ignored. None if the input does not parse or the sequence length differs from
+/// the number of bases.
+pub fn pair_composition(input: &str, seq: &str) -> Option<PairComposition> {
+    let pt = parser::parse(input).ok()?;
+    let seq: Vec<u8> = seq
+        .bytes()
+        .filter(|&c| c != b'+' && c != b'&')
+        .map(|c| match c.to_ascii_uppercase() {
+            b'T' => b'U',
+            c => c,
+        })
+        .collect();
+    if seq.len() != pt.n_bases {
+        return None;
+    }
+    let mut comp = PairComposition::default();
+    for (i, &j) in pt.pairs.iter().enumerate().filter(|&(i, &j)| i < j) {
+        let (a, b) = (seq[i].min(seq[j]), seq[i].max(seq[j]));
+        let count = match (a, b) {
+            (b'C', b'G') => &mut comp.gc,
+            (b'A', b'U') => &mut comp.au,
+            (b'G', b'U') => &mut comp.gu,
+            _ if b"ACGU".contains(&a) && b"ACGU".contains(&b) => &mut comp.mismatch,
+            _ => &mut comp.ambiguous,
+        };
+        *count += 1;
+    }
+    Some(comp)
+}
+
+/// Compute the layout for a pair table built directly (e.g. from 3D structure
+/// annotation) instead of parsed from dot-bracket.
+///
+/// The table must satisfy what `parse` guarantees: `pairs` has `n_bases` entries,
+/// is symmetric (`pairs[pairs[i]] == i`) and non-crossing, and `nicks` is strictly
+/// increasing and starts with 0. Returns None otherwise.
+pub fn draw_from_pairtable(pt: &PairTable) -> Option<DrawResult> {
+    if !is_valid_pair_table(pt) {
+        return None;
+    }
+    layout_pair_table(pt, &LayoutOptions::default())
+}
+
+/// Flat base coordinates, pairs, and strand ranges for simple plotting.
+pub fn coords(input: &str) -> Option<Coords> {
+    let r = layout(input)?;
+    let xs = r.layout.bases.iter().map(|b| b.x).collect();
+    let ys = r.layout.bases.iter().map(|b| b.y).collect();
+    let pairs = r
+        .pairs
+        .iter()
+        .enumerate()
+        .filter(|&(i, &j)| i < j)
+        .map(|(i, &j)| (i, j))
+        .collect();
+    let strands = r.strands();
+    Some(Coords {
+        xs,
+        ys,
+        pairs,
+        strands,
+    })
+}
+
+/// Smallest distance between any two base centers, for rejecting crowded layouts.
+///
+/// Buckets bases into a grid whose cell size is the shortest backbone step, which
+/// bounds the answer, so only neighboring cells are compared. Infinite for fewer
+/// than two bases.
+pub fn min_base_distance(result: &DrawResult) -> f64 {
+    let bases = &result.layout.bases;
+    let dist = |a: &Base, b: &Base| (a.x - b.x).hypot(a.y - b.y);
+    let cell = bases
+        .windows(2)
+        .map(|w| dist(&w[0], &w[1]))
+        .fold(f64::INFINITY, f64::min);
+    if !(cell > 0.0 && cell.is_finite()) {
+        return cell;
+    }
+
+    let key = |b: &Base| ((b.x / cell).floor() as i64, (b.y / cell).floor() as i64);
+    let mut grid: std::collections::HashMap<(i64, i64), Vec<usize>> =
+        std::collections::HashMap::new();
+    for (i, b) in bases.iter().enumerate() {
+        grid.entry(key(b)).or_default().push(i);
+    }
+    let mut best = cell;
+    for (i, b) in bases.iter().enumerate() {
+        let (cx, cy) = key(b);
+        for gx in cx - 1..=cx + 1 {
+            for gy in cy - 1..=cy + 1 {
+                for &j in grid.get(&(gx, gy)).into_iter().flatten() {
+                    if j > i {
+                        best = best.min(dist(b, &bases[j]));
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Total length of the backbone path: line lengths plus arc lengths (`r·|t1 − t2|`
+/// on circles).
+pub fn backbone_length(result: &DrawResult) -> f64 {
+    result
+        .segments
+        .iter()
+        .flatten()
+        .map(|seg| match seg {
+            Segment::Line(l) => (l.x1 - l.x).hypot(l.y1 - l.y),
+            Segment::Arc(a) => a.length(),
+        })
+        .sum()
+}
+
+/// Unit vector pointing away from the fold at base `i`, for placing callouts.
+///
+/// Unpaired bases point from their loop center through the base (the same
+/// direction as their `xt`/`yt` offset). Paired bases point from their partner
+/// through the base, i.e. sideways out of the helix. If that is degenerate, the
+/// direction from the centroid of all bases is used, then straight up.
+///
+/// Panics if `i` is not a base index.
+pub fn outward_direction(result: &DrawResult, i: usize) -> (f64, f64) {
+    let bases = &result.layout.bases;
+    let b = &bases[i];
+    let j = result.pairs[i];
+    let from = if j == i {
+        let l = &result.layout.loops[b.loop1];
+        (l.x, l.y)
+    } else {
+        (bases[j].x, bases[j].y)
+    };
+    let n = bases.len() as f64;
+    let centroid = (
+        bases.iter().map(|b| b.x).sum::<f64>() / n,
+        bases.iter().map(|b| b.y).sum::<f64>() / n,
+    );
+    [from, centroid]
+        .into_iter()
+        .find_map(|(x, y)| {
+            let (dx, dy) = (b.x - x, b.y - y);
+            let len = dx.hypot(dy);
+            (len > 1e-9).then(|| (dx / len, dy / len))
+        })
+        .unwrap_or((0.0, 1.0))
+}
+
+/// Render dot-bracket-plus notation as SVG.
+///
+/// Returns an empty string on any `try_draw_svg` error, including a sequence
+/// whose length (strand breaks excluded) differs from the number of bases; such
+/// sequences used to be drawn with misaligned labels.
+pub fn draw_svg(input: &str, seq: Option<&str>, opts: &svg::SvgOptions) -> String {
+    try_draw_svg(input, seq, opts).unwrap_or_default()
+}
+
+/// Like `draw_svg`, reporting why nothing was drawn (parse error, empty input,
+/// pseudoknot, no pairs, sequence length mismatch) instead of returning an empty
+/// string.
+pub fn try_draw_svg(
+    input: &str,
+    seq: Option<&str>,
+    opts: &svg::SvgOptions,
+) -> Result<String, DrawError> {
+    let (r, _) = prepare_svg(input, seq, opts, None)?;
+    Ok(svg::render_with_source(&r, seq, input, opts))
+}
+
+/// Alias of `try_draw_svg`, which rejects a sequence whose length (strand breaks
+/// excluded) differs from the number of bases.
+pub fn draw_svg_checked(
+    input: &str,
+    seq: Option<&str>,
+    opts: &svg::SvgOptions,
+) -> Result<String, DrawError> {
+    try_draw_svg(input, seq, opts)
+}
+
+/// Like `draw_svg`, writing the SVG into `w` instead of returning it.
+///
+/// Invalid input writes nothing and returns Ok; only write errors are reported.
+pub fn draw_svg_to<W: std::fmt::Write>(
+    w: &mut W,
+    input: &str,
+    seq: Option<&str>,
+    opts: &svg::SvgOptions,
+) -> std::fmt::Result {
+    match prepare_svg(input, seq, opts, None) {
+        Ok((r, _)) => svg::render_with_source_to(w, &r, seq, input, opts),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Like `draw_svg`, also returning the rotation (radians) applied to the layout.
+///
+/// With `forced` set, that angle is applied instead of the automatic stem
+/// alignment, so passing back a previously returned angle keeps orientation
+/// stable across frames of an animation. Without it, the angle comes from
+/// `align_stem` (0.0 when disabled or not applicable). Invalid input yields an
+/// empty string and 0.0.
+pub fn draw_svg_with_rotation(
+    input: &str,
+    seq: Option<&str>,
+    opts: &svg::SvgOptions,
+    forced: Option<f64>,
+) -> (String, f64) {
+    match prepare_svg(input, seq, opts, forced) {
+        Ok((r, angle)) => (svg::render_with_source(&r, seq, input, opts), angle),
+        Err(_) => (String::new(), 0.0),
+    }
+}
+
+/// Lay out `input` for SVG rendering, check that `seq` (if any) has one letter
+/// per base, and apply the forced or stem-aligning rotation.
+fn prepare_svg(
+    input: &str,
+    seq: Option<&str>,
+    opts: &svg::SvgOptions,
+    forced: Option<f64>,
+) -> Result<(DrawResult, f64), DrawError> {
+    let mut r = try_compute_draw_result(input, &opts.layout)?;
+    if let Some(seq) = seq {
+        let sequence = seq.chars().filter(|&c| c != '+' && c != '&').count();
+        let bases = r.layout.bases.len();
+        if sequence != bases {
+            return Err(DrawError::SequenceLength { sequence, bases });
+        }
+    }
+    let angle = forced
+        .or_else(|| opts.align_stem.then(|| compute_stem_rotation(&r)).flatten())
+        .unwrap_or(0.0);
+    if angle != 0.0 {
+        r.rotate(angle);
+    }
+    Ok((r, angle))
+}
+
 /// Compute the rotation angle needed to align the primary stem vertically.
 ///
+/// The primary stem is closed by the outermost pair enclosing the most bases
+/// (ties go to the 5′-most), so a short 5′ hairpin or leader does not decide the
+/// orientation of a larger fold.
+///
 /// Strategy:
 /// 1. If nested inner pair exists, use the direction between pair midpoints (most accurate).
-/// 2. Otherwise, make the first pair bond horizontal (stem perpendicular = vertical).
+/// 2. Otherwise, make the primary pair bond horizontal (stem perpendicular = vertical).
 fn compute_stem_rotation(result: &DrawResult) -> Option<f64> {
     let pairs = &result.pairs;
     let bases = &result.layout.bases;
 
-    // Find first pair (smallest i where pairs[i] > i)
-    let i = pairs
+    // Widest pair (largest j - i where pairs[i] = j > i)
+    let (i, j) = pairs
         .iter()
         .enumerate()
-        .find(|&(idx, &p)| p != idx && idx < p)
-        .map(|(idx, _)| idx)?;
-    let j = pairs[i];
+        .filter(|&(idx, &p)| idx < p)
+        .map(|(idx, &p)| (idx, p))
+        .min_by_key(|&(idx, p)| (std::cmp::Reverse(p - idx), idx))?;
 
     // Primary: midpoint direction when nested inner pair exists
     if j > 1 && i + 1 < j - 1 && pairs[i + 1] == j - 1 {
@@ -90,48 +500,35 @@ fn compute_stem_rotation(result: &DrawResult) -> Option<f64> {
     Some(-pair_angle)
 }
 
-/// Rotate all coordinates in a DrawResult around the origin.
-fn rotate_result(result: &mut DrawResult, angle: f64) {
-    let cos_a = angle.cos();
-    let sin_a = angle.sin();
-
+/// Mirror a DrawResult across the x axis (y → -y, angles → -angle).
+fn flip_y(result: &mut DrawResult) {
     for b in &mut result.layout.bases {
-        let (x, y) = (b.x, b.y);
-        b.x = x * cos_a - y * sin_a;
-        b.y = x * sin_a + y * cos_a;
-        let (xt, yt) = (b.xt, b.yt);
-        b.xt = xt * cos_a - yt * sin_a;
-        b.yt = xt * sin_a + yt * cos_a;
-        b.angle1 += angle;
-        b.angle2 += angle;
+        b.y = -b.y;
+        b.yt = -b.yt;
+        b.angle1 = -b.angle1;
+        b.angle2 = -b.angle2;
     }
-
     for l in &mut result.layout.loops {
-        let (x, y) = (l.x, l.y);
-        l.x = x * cos_a - y * sin_a;
-        l.y = x * sin_a + y * cos_a;
+        l.y = -l.y;
+        if let Some(e) = &mut l.ellipse {
+            e.rotation = -e.rotation;
+        }
         for p in &mut l.pairs {
-            p.angle += angle;
+            p.angle = -p.angle;
         }
     }
-
-    for segs in &mut result.segments {
-        for seg in segs {
-            match seg {
-                Segment::Line(line) => {
-                    let (x, y) = (line.x, line.y);
-                    line.x = x * cos_a - y * sin_a;
-                    line.y = x * sin_a + y * cos_a;
-                    let (x1, y1) = (line.x1, line.y1);
-                    line.x1 = x1 * cos_a - y1 * sin_a;
-                    line.y1 = x1 * sin_a + y1 * cos_a;
-                }
-                Segment::Arc(arc) => {
-                    let (x, y) = (arc.x, arc.y);
-                    arc.x = x * cos_a - y * sin_a;
-                    arc.y = x * sin_a + y * cos_a;
-                    arc.t1 += angle;
-                    arc.t2 += angle;
+    for seg in result.segments.iter_mut().flatten() {
+        match seg {
+            Segment::Line(line) => {
+                line.y = -line.y;
+                line.y1 = -line.y1;
+            }
+            Segment::Arc(arc) => {
+                arc.y = -arc.y;
+                arc.t1 = -arc.t1;
+                arc.t2 = -arc.t2;
+                if let Some(e) = &mut arc.ellipse {
+                    e.rotation = -e.rotation;
                 }
             }
         }
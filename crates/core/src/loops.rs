@@ -1,4 +1,4 @@
-use crate::types::{LoopInfo, PairTable};
+use crate::types::{LoopInfo, LoopKind, PairTable, StructureStats};
 
 /// Decompose the pair table into a hierarchical loop tree.
 ///
@@ -9,6 +9,11 @@ use crate::types::{LoopInfo, PairTable};
 ///     assigned in sequence, processed in reverse.
 ///   - Internal levels: assign ALL children in sequence, process subtrees
 ///     in reverse.
+///
+/// The ground-truth fixtures depend on this order, so it is stable. E.g. for
+/// `((..))(((..)))((..))` the loops closed by (0,5), (1,4), (6,13), (14,19),
+/// (15,18), (7,12) get indices 1..=6. Use `DrawResult::loop_index_of_pair`
+/// rather than re-deriving it.
 pub fn decompose(pt: &PairTable) -> Vec<LoopInfo> {
     let n = pt.n_bases;
     if n == 0 {
@@ -29,32 +34,21 @@ pub fn decompose(pt: &PairTable) -> Vec<LoopInfo> {
     }
 
     // Step 2: For each pair, find the smallest enclosing pair.
+    // Pairs are non-crossing, so one stack pass suffices: the smallest enclosing
+    // pair is whatever is open when a pair opens. all_pairs is sorted by i, so
+    // the k-th opening bracket is pair k.
     let mut enclosing: Vec<Option<usize>> = vec![None; all_pairs.len()];
-    let mut pair_indices: Vec<usize> = (0..all_pairs.len()).collect();
-    pair_indices.sort_by_key(|&k| {
-        let (i, j) = all_pairs[k];
-        j - i
-    });
-
-    for idx in 0..pair_indices.len() {
-        let k = pair_indices[idx];
-        let (ki, kj) = all_pairs[k];
-        let mut best: Option<usize> = None;
-        let mut best_span = usize::MAX;
-        for &m in &pair_indices {
-            if m == k {
-                continue;
-            }
-            let (mi, mj) = all_pairs[m];
-            if mi < ki && kj < mj {
-                let span = mj - mi;
-                if span < best_span {
-                    best_span = span;
-                    best = Some(m);
-                }
-            }
+    let mut open: Vec<usize> = Vec::new();
+    let mut next_k = 0;
+    for i in 0..n {
+        let j = pt.pairs[i];
+        if j > i {
+            enclosing[next_k] = open.last().copied();
+            open.push(next_k);
+            next_k += 1;
+        } else if j < i {
+            open.pop();
         }
-        enclosing[k] = best;
     }
 
     // Step 3: Group pairs by parent.
@@ -85,19 +79,7 @@ pub fn decompose(pt: &PairTable) -> Vec<LoopInfo> {
     // External loop (loop 0)
     let ext_child_pairs: Vec<(usize, usize)> =
         external_children.iter().map(|&k| all_pairs[k]).collect();
-    let mut ext_unpaired = Vec::new();
-    {
-        let mut enclosed = vec![false; n];
-        for &k in &external_children {
-            let (i, j) = all_pairs[k];
-            enclosed[i..=j].fill(true);
-        }
-        for (b, &enc) in enclosed.iter().enumerate() {
-            if !enc && pt.pairs[b] == b {
-                ext_unpaired.push(b);
-            }
-        }
-    }
+    let ext_unpaired = unpaired_between(pt, 0, n);
     loops.push(LoopInfo {
         parent_pair: None,
         child_pairs: ext_child_pairs,
@@ -168,26 +150,31 @@ fn push_loop(
         .iter()
         .map(|&ck| all_pairs[ck])
         .collect();
-    let mut unpaired = Vec::new();
-    let mut child_enclosed = vec![false; pt.n_bases];
-    for &ck in &children_of_pair[k] {
-        let (ci, cj) = all_pairs[ck];
-        child_enclosed[ci..=cj].fill(true);
-    }
-    #[allow(clippy::needless_range_loop)] // b indexes both child_enclosed and pt.pairs
-    for b in (i + 1)..j {
-        if !child_enclosed[b] && pt.pairs[b] == b {
-            unpaired.push(b);
-        }
-    }
     loops.push(LoopInfo {
         parent_pair: Some((i, j)),
         child_pairs: children,
-        unpaired_bases: unpaired,
+        unpaired_bases: unpaired_between(pt, i + 1, j),
         nicks_in_loop: vec![],
     });
 }
 
+/// Unpaired bases in `start..end` that are not enclosed by a pair, skipping
+/// over each pair's interior in one jump.
+fn unpaired_between(pt: &PairTable, start: usize, end: usize) -> Vec<usize> {
+    let mut unpaired = Vec::new();
+    let mut b = start;
+    while b < end {
+        let p = pt.pairs[b];
+        if p == b {
+            unpaired.push(b);
+        } else if p > b {
+            b = p;
+        }
+        b += 1;
+    }
+    unpaired
+}
+
 /// Recursively process a subtree: assign all children of parent_k,
 /// then process subtrees in reverse order.
 /// Uses an explicit stack to handle deep stems without stack overflow.
@@ -312,3 +299,56 @@ fn assign_nicks_to_loops(loops: &mut [LoopInfo], pt: &PairTable, _all_pairs: &[(
         }
     }
 }
+
+/// Count loops by kind, base pairs, and helices (runs of stacked pairs).
+pub(crate) fn structure_stats(infos: &[LoopInfo], pt: &PairTable) -> StructureStats {
+    let mut stats = StructureStats::default();
+    for info in infos {
+        match loop_kind(info) {
+            LoopKind::Hairpin => stats.hairpins += 1,
+            LoopKind::Bulge => stats.bulges += 1,
+            LoopKind::Interior => stats.interior_loops += 1,
+            LoopKind::Multi => stats.multiloops += 1,
+            LoopKind::External | LoopKind::Stack => {}
+        }
+    }
+    let n = pt.pairs.len();
+    for (i, &j) in pt.pairs.iter().enumerate() {
+        // Count each helix from its outermost pair
+        let stacked_on = |i: usize, j: usize| i > 0 && j + 1 < n && pt.pairs[i - 1] == j + 1;
+        if j <= i || stacked_on(i, j) {
+            continue;
+        }
+        stats.base_pairs += 1;
+        stats.stems += 1;
+        let mut len = 1;
+        while i + len < j && pt.pairs[i + len] == j - len {
+            len += 1;
+        }
+        stats.base_pairs += len - 1;
+        stats.longest_stem = stats.longest_stem.max(len);
+    }
+    stats
+}
+
+/// Classify a loop by its closing pair, child pairs, and unpaired bases.
+///
+/// Nicks are ignored. An empty hairpin (no unpaired bases) is still a Hairpin.
+pub fn loop_kind(info: &LoopInfo) -> LoopKind {
+    let Some((pi, pj)) = info.parent_pair else {
+        return LoopKind::External;
+    };
+    match info.child_pairs.as_slice() {
+        [] => LoopKind::Hairpin,
+        &[(ci, cj)] => {
+            let left = info.unpaired_bases.iter().any(|&b| pi < b && b < ci);
+            let right = info.unpaired_bases.iter().any(|&b| cj < b && b < pj);
+            match (left, right) {
+                (false, false) => LoopKind::Stack,
+                (true, true) => LoopKind::Interior,
+                _ => LoopKind::Bulge,
+            }
+        }
+        _ => LoopKind::Multi,
+    }
+}
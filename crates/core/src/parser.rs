@@ -1,48 +1,91 @@
-use crate::types::PairTable;
+use crate::types::{PairTable, ParseError, ParseOptions};
 
 /// Parse dot-bracket-plus notation into a pair table.
 ///
-/// Characters: `(` = open pair, `)` = close pair, `.` = unpaired, `+` = strand break
+/// Characters: `(` = open pair, `)` = close pair, `.` = unpaired, `+` or `&` = strand
+/// break (`&` as written by NUPACK and newer ViennaRNA).
+/// `<`/`>` form a second pairing level merged into the same pair table; the two
+/// levels may nest inside each other but must not cross.
 ///
-/// Returns Err on invalid input (bad characters, unmatched parens).
+/// Every strand must contain at least one base, so leading, trailing, or
+/// consecutive breaks are rejected rather than producing duplicate nicks.
+///
+/// ASCII whitespace (space, tab, newline, carriage return) is skipped without
+/// counting as a base, so pasted input with a trailing newline parses as is.
+///
+/// A trailing free-energy annotation as printed by folding tools, e.g.
+/// `(((...))) (-3.40)`, is ignored (see `strip_energy`).
+///
+/// Returns Err on invalid input (bad characters, unmatched or crossing brackets,
+/// empty strands), with the `ParseError` message.
 pub fn parse(input: &str) -> Result<PairTable, String> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+/// Like `parse`, additionally treating `opts.unpaired_chars` as unpaired bases.
+pub fn parse_with_options(input: &str, opts: &ParseOptions) -> Result<PairTable, String> {
+    parse_typed(input, opts).map_err(|e| e.to_string())
+}
+
+/// `parse_with_options` with the typed error, for `DrawError` and `parse_vienna`.
+pub(crate) fn parse_typed(input: &str, opts: &ParseOptions) -> Result<PairTable, ParseError> {
+    let input = strip_energy(input);
     let mut pairs: Vec<usize> = Vec::new();
     let mut nicks: Vec<usize> = vec![0]; // always starts with 0
-    let mut stack: Vec<usize> = Vec::new();
+    // (base index, opening bracket)
+    let mut stack: Vec<(usize, char)> = Vec::new();
     let mut base_idx: usize = 0;
 
     for ch in input.chars() {
         match ch {
-            '(' => {
+            '(' | '<' => {
                 pairs.push(0); // placeholder
-                stack.push(base_idx);
+                stack.push((base_idx, ch));
                 base_idx += 1;
             }
-            ')' => {
-                let j = stack.pop().ok_or("unmatched ) parenthesis")?;
+            ')' | '>' => {
+                let open = if ch == ')' { '(' } else { '<' };
+                let j = match stack.pop() {
+                    Some((j, c)) if c == open => j,
+                    Some(_) if stack.iter().any(|&(_, c)| c == open) => {
+                        // "(<)" is an unclosed `<`, not a crossing pair
+                        return Err(match unmatched_bracket(input) {
+                            Some(c) => ParseError::Unmatched(c),
+                            None => ParseError::Crossing(open, ch),
+                        });
+                    }
+                    _ => return Err(ParseError::Unmatched(ch)),
+                };
                 pairs.push(0); // placeholder
                 pairs[j] = base_idx;
                 pairs[base_idx] = j;
                 base_idx += 1;
             }
-            '.' => {
+            _ if ch == '.' || opts.unpaired_chars.contains(&ch) => {
                 pairs.push(base_idx); // self-paired = unpaired
                 base_idx += 1;
             }
-            '+' => {
+            '+' | '&' => {
+                if nicks.last() == Some(&base_idx) {
+                    return Err(ParseError::EmptyStrand);
+                }
                 nicks.push(base_idx);
             }
+            ' ' | '\t' | '\n' | '\r' => {}
             _ => {
-                return Err("bad dot-parens character".to_string());
+                return Err(ParseError::BadCharacter(ch));
             }
         }
     }
 
-    if !stack.is_empty() {
-        return Err("unmatched ( parenthesis".to_string());
+    if let Some(&(_, c)) = stack.last() {
+        return Err(ParseError::Unmatched(c));
     }
 
     let n_bases = base_idx;
+    if nicks.len() > 1 && nicks.last() == Some(&n_bases) {
+        return Err(ParseError::EmptyStrand);
+    }
     Ok(PairTable {
         pairs,
         nicks,
@@ -50,6 +93,121 @@ pub fn parse(input: &str) -> Result<PairTable, String> {
     })
 }
 
+/// First bracket without a partner of its own kind, counting `()` and `<>`
+/// separately, or None if both levels balance.
+fn unmatched_bracket(input: &str) -> Option<char> {
+    for (open, close) in [('(', ')'), ('<', '>')] {
+        let mut depth = 0usize;
+        for ch in input.chars() {
+            if ch == open {
+                depth += 1;
+            } else if ch == close {
+                let Some(d) = depth.checked_sub(1) else {
+                    return Some(close);
+                };
+                depth = d;
+            }
+        }
+        if depth > 0 {
+            return Some(open);
+        }
+    }
+    None
+}
+
+/// Parse Vienna (RNAfold-style) output: an optional sequence line followed by a
+/// structure line, which may carry a trailing energy such as `(((...))) (-3.40)`.
+///
+/// Blank lines and `>` header lines are skipped. With a single line it is taken
+/// as the structure and no sequence is returned. The sequence may contain `+` or
+/// `&` strand breaks like the structure; its base count must match.
+///
+/// Only the first record is read: anything after its structure line, such as the
+/// ensemble and centroid lines of `RNAfold -p` or further records, is ignored.
+pub fn parse_vienna(input: &str) -> Result<(PairTable, Option<String>), ParseError> {
+    let mut lines = input
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('>'));
+    let first = lines.next().ok_or(ParseError::MissingStructure)?;
+    let (seq, structure) = match lines.next() {
+        Some(second) => (Some(first), second),
+        None => (None, first),
+    };
+    let pt = parse_typed(structure, &ParseOptions::default())?;
+    if let Some(seq) = seq {
+        let len = seq.chars().filter(|&c| c != '+' && c != '&').count();
+        if len != pt.n_bases {
+            return Err(ParseError::LengthMismatch {
+                sequence: len,
+                structure: pt.n_bases,
+            });
+        }
+    }
+    Ok((pt, seq.map(String::from)))
+}
+
+/// Build a pair table from a partner array (`partners[i]` = partner of base i, or
+/// -1 if unpaired), as produced by many folding programs.
+///
+/// `nicks` lists strand start positions; a leading 0 is optional. Pairs must be
+/// symmetric and in range; crossing pairs (pseudoknots) are kept, but
+/// `draw_from_pairtable` only lays out nested ones.
+pub fn from_partners(partners: &[isize], nicks: &[usize]) -> Result<PairTable, ParseError> {
+    let n = partners.len();
+    let mut pairs = Vec::with_capacity(n);
+    for (i, &p) in partners.iter().enumerate() {
+        let j = match usize::try_from(p) {
+            Ok(j) if j < n && j != i => j,
+            _ if p == -1 => i,
+            _ => return Err(ParseError::BadPartner(i)),
+        };
+        if j != i && partners[j] != i as isize {
+            return Err(ParseError::Asymmetric(i, j));
+        }
+        pairs.push(j);
+    }
+
+    let mut starts = vec![0];
+    for &k in nicks.iter().skip_while(|&&k| k == 0) {
+        if k > n || starts.last().is_some_and(|&last| k < last) {
+            return Err(ParseError::BadNick(k));
+        }
+        if starts.last() == Some(&k) || k == n {
+            return Err(ParseError::EmptyStrand);
+        }
+        starts.push(k);
+    }
+    Ok(PairTable {
+        pairs,
+        nicks: starts,
+        n_bases: n,
+    })
+}
+
+/// Drop a trailing whitespace-separated `(number)` energy token, if present,
+/// along with the whitespace before it. Other input, including non-finite
+/// tokens such as `(nan)` or `(inf)`, is returned unchanged.
+///
+/// `"(((...))) ( -3.40)"` becomes `"(((...)))"`.
+pub fn strip_energy(line: &str) -> &str {
+    let trimmed = line.trim_end();
+    let Some(open) = trimmed.rfind('(') else {
+        return line;
+    };
+    let (head, token) = trimmed.split_at(open);
+    let is_energy = token
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .and_then(|e| e.trim().parse::<f64>().ok())
+        .is_some_and(f64::is_finite);
+    if is_energy && head.ends_with(char::is_whitespace) {
+        head.trim_end()
+    } else {
+        line
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,6 +235,29 @@ mod tests {
         assert_eq!(pt.n_bases, 8);
     }
 
+    #[test]
+    fn test_ampersand_nick() {
+        let plus = parse("((.+.))").unwrap();
+        let amp = parse("((.&.))").unwrap();
+        assert_eq!(amp.pairs, plus.pairs);
+        assert_eq!(amp.nicks, vec![0, 3]);
+        assert_eq!(parse("((+.&.))").unwrap().nicks, vec![0, 2, 3]);
+        assert_eq!(parse("(&&)").err(), Some("empty strand".to_string()));
+
+        let (pt, seq) = parse_vienna("GG&CC\n((&))").unwrap();
+        assert_eq!(pt.nicks, vec![0, 2]);
+        assert_eq!(seq.as_deref(), Some("GG&CC"));
+    }
+
+    #[test]
+    fn test_empty_strands() {
+        assert!(parse("++").is_err());
+        assert!(parse("((++))").is_err());
+        assert!(parse("+(())").is_err());
+        assert!(parse("(())+").is_err());
+        assert!(parse("(+)").is_ok());
+    }
+
     #[test]
     fn test_unmatched_open() {
         assert!(parse("((..)").is_err());
@@ -87,8 +268,172 @@ mod tests {
         assert!(parse("())").is_err());
     }
 
+    #[test]
+    fn test_angle_brackets() {
+        let pt = parse("((<..>))").unwrap();
+        assert_eq!(pt.pairs, vec![7, 6, 5, 3, 4, 2, 1, 0]);
+        let pt = parse("<(..)>").unwrap();
+        assert_eq!(pt.pairs, vec![5, 4, 2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_angle_brackets_unmatched() {
+        assert!(parse("<<..>").is_err());
+        assert!(parse("(..>").is_err());
+    }
+
+    #[test]
+    fn test_angle_brackets_crossing() {
+        assert!(parse("(<)>").is_err());
+        assert!(parse("<(>)").is_err());
+        assert_eq!(
+            parse_typed("(<)>", &ParseOptions::default()).err(),
+            Some(ParseError::Crossing('(', ')'))
+        );
+    }
+
+    #[test]
+    fn test_unmatched_reported_before_crossing() {
+        let err = |s: &str| parse_typed(s, &ParseOptions::default()).err();
+        assert_eq!(err("(<)"), Some(ParseError::Unmatched('<')));
+        assert_eq!(err("<(>"), Some(ParseError::Unmatched('(')));
+        assert_eq!(err("(<)>>"), Some(ParseError::Unmatched('>')));
+    }
+
+    #[test]
+    fn test_unpaired_chars() {
+        let opts = ParseOptions {
+            unpaired_chars: vec![':', '_'],
+        };
+        let pt = parse_with_options("((:_.))", &opts).unwrap();
+        assert_eq!(pt.pairs, vec![6, 5, 2, 3, 4, 1, 0]);
+        assert!(parse("((:_.))").is_err());
+        assert!(parse_with_options("((,))", &opts).is_err());
+    }
+
     #[test]
     fn test_bad_char() {
         assert!(parse("(x)").is_err());
+        assert_eq!(
+            parse_typed("(x)", &ParseOptions::default()).err(),
+            Some(ParseError::BadCharacter('x'))
+        );
+    }
+
+    #[test]
+    fn test_whitespace_ignored() {
+        let table = |s: &str| parse(s).map(|pt| (pt.pairs, pt.nicks, pt.n_bases));
+        let expected = table("(((...)))").unwrap();
+        for input in [" (((...)))\n", "\t(((...)))\r\n", "((( ... )))"] {
+            assert_eq!(table(input).unwrap(), expected, "{input:?}");
+        }
+        // Indices are unaffected: the break still falls after base 2
+        assert_eq!(table(" ((.\n+ .))\n"), table("((.+.))"));
+        assert_eq!(table("(((...))) (-3.40)\n").unwrap(), expected);
+        assert_eq!(
+            parse_typed("( \u{a0})", &ParseOptions::default()).err(),
+            Some(ParseError::BadCharacter('\u{a0}'))
+        );
+    }
+
+    #[test]
+    fn test_from_partners() {
+        let pt = from_partners(&[5, 4, -1, -1, 1, 0], &[3]).unwrap();
+        let parsed = parse("((.+.))").unwrap();
+        assert_eq!(pt.pairs, parsed.pairs);
+        assert_eq!(pt.nicks, parsed.nicks);
+        assert_eq!(pt.n_bases, 6);
+        assert_eq!(from_partners(&[-1, -1], &[0]).unwrap().nicks, vec![0]);
+
+        assert_eq!(
+            from_partners(&[2, -1, -1], &[]).err(),
+            Some(ParseError::Asymmetric(0, 2))
+        );
+        assert_eq!(
+            from_partners(&[-1, 7], &[]).err(),
+            Some(ParseError::BadPartner(1))
+        );
+        assert_eq!(
+            from_partners(&[-2, -1], &[]).err(),
+            Some(ParseError::BadPartner(0))
+        );
+        assert_eq!(
+            from_partners(&[0], &[]).err(),
+            Some(ParseError::BadPartner(0))
+        );
+        assert_eq!(
+            from_partners(&[-1, -1, -1], &[2, 1]).err(),
+            Some(ParseError::BadNick(1))
+        );
+        assert_eq!(
+            from_partners(&[-1, -1], &[4]).err(),
+            Some(ParseError::BadNick(4))
+        );
+        assert_eq!(
+            from_partners(&[-1, -1], &[1, 1]).err(),
+            Some(ParseError::EmptyStrand)
+        );
+        assert_eq!(
+            from_partners(&[-1, -1], &[2]).err(),
+            Some(ParseError::EmptyStrand)
+        );
+    }
+
+    #[test]
+    fn test_strip_energy() {
+        assert_eq!(strip_energy("(((...))) (-3.4)"), "(((...)))");
+        assert_eq!(strip_energy("(((...)))\t( -3.40)\n"), "(((...)))");
+        assert_eq!(strip_energy("(((...)))"), "(((...)))");
+        assert_eq!(strip_energy("(((...)))(-3.4)"), "(((...)))(-3.4)");
+        assert_eq!(strip_energy("(((...))) (abc)"), "(((...))) (abc)");
+        assert_eq!(strip_energy("(((...))) (nan)"), "(((...))) (nan)");
+        assert_eq!(strip_energy("(((...))) (inf)"), "(((...))) (inf)");
+        assert_eq!(
+            strip_energy("(((...))) (-infinity)"),
+            "(((...))) (-infinity)"
+        );
+
+        let pt = parse("(((...))) (-3.4)").unwrap();
+        assert_eq!(pt.n_bases, 9);
+        // The energy token itself is not a structure
+        assert!(parse("(-3.4)").is_err());
+    }
+
+    #[test]
+    fn test_parse_vienna() {
+        let (pt, seq) = parse_vienna(">hp\nGGGAAACCC\n(((...))) ( -3.40)\n").unwrap();
+        assert_eq!(pt.pairs, vec![8, 7, 6, 3, 4, 5, 2, 1, 0]);
+        assert_eq!(seq.as_deref(), Some("GGGAAACCC"));
+
+        let (pt, seq) = parse_vienna("((..)) (-1.2)\n").unwrap();
+        assert_eq!(pt.n_bases, 6);
+        assert!(seq.is_none());
+
+        // Energy must be whitespace-separated
+        assert!(parse_vienna("((..))(-1.2)").is_err());
+
+        // Only the first record counts
+        let rnafold_p =
+            "GGGAAACCC\n(((...))) (-3.40)\n(((,..))) [-3.52]\n(((...))) {-3.40 d=0.51}\n";
+        let (pt, seq) = parse_vienna(rnafold_p).unwrap();
+        assert_eq!(pt.pairs, vec![8, 7, 6, 3, 4, 5, 2, 1, 0]);
+        assert_eq!(seq.as_deref(), Some("GGGAAACCC"));
+        let (pt, seq) = parse_vienna(">a\nGGAACC\n((..))\n>b\nGAAAC\n(...)\n").unwrap();
+        assert_eq!((pt.n_bases, seq.as_deref()), (6, Some("GGAACC")));
+    }
+
+    #[test]
+    fn test_parse_vienna_errors() {
+        assert_eq!(
+            parse_vienna("\n\n").err(),
+            Some(ParseError::MissingStructure)
+        );
+        assert_eq!(
+            parse_vienna("GGAAC\n(((...)))").err(),
+            Some(ParseError::LengthMismatch {
+                sequence: 5,
+                structure: 9
+            })
+        );
     }
 }
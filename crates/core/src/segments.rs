@@ -1,3 +1,4 @@
+use crate::geometry::terminal_tails;
 use crate::types::*;
 
 /// Generate backbone segments from layout.
@@ -7,143 +8,230 @@ use crate::types::*;
 /// - Nick in unpaired loop → degenerate ARC (t1 == t2)
 /// - Pair-only loop connection → LINE from base to midpoint of consecutive bases
 /// - Loop with unpaired bases → ARC on loop circle, split at midpoint angle
+///
+/// `opts.segment_mode` can force arcs or lines everywhere instead.
 pub fn generate(
     loops: &[Loop],
     bases: &[Base],
     pt: &PairTable,
     loop_infos: &[LoopInfo],
+    opts: &LayoutOptions,
 ) -> Vec<Vec<Segment>> {
     let n = pt.n_bases;
     if n == 0 {
         return vec![];
     }
 
-    let nick_set: std::collections::HashSet<usize> = pt.nicks.iter().copied().collect();
+    let ctx = LoopContext {
+        bases,
+        loops,
+        infos: loop_infos,
+        pt,
+        opts,
+        nicks: pt.nicks.iter().copied().collect(),
+    };
 
     let mut segments: Vec<Vec<Segment>> = Vec::with_capacity(n);
 
+    // Straightened terminal tails and the links into them are plain lines
+    let mut in_tail = vec![false; n];
+    if opts.straight_tails {
+        let (lead, trail) = terminal_tails(pt);
+        lead.chain(trail).for_each(|b| in_tail[b] = true);
+    }
+
     for i in 0..n {
-        let incoming = compute_incoming(i, n, bases, loops, loop_infos, &nick_set);
-        let outgoing = compute_outgoing(i, n, bases, loops, loop_infos, &nick_set);
+        let incoming = if in_tail[i] || (i > 0 && in_tail[i - 1]) {
+            linear_incoming(i, bases, pt)
+        } else {
+            ctx.incoming(i)
+        };
+        let outgoing = if in_tail[i] || (i + 1 < n && in_tail[i + 1]) {
+            linear_outgoing(i, bases, pt)
+        } else {
+            ctx.outgoing(i)
+        };
         segments.push(vec![incoming, outgoing]);
     }
 
     segments
 }
 
-/// Incoming segment for base i (from base i-1 side).
-fn compute_incoming(
-    i: usize,
-    n: usize,
-    bases: &[Base],
-    loops: &[Loop],
-    infos: &[LoopInfo],
-    nicks: &std::collections::HashSet<usize>,
-) -> Segment {
-    // Nick at position i means break between base i-1 and base i
-    if nicks.contains(&i) {
+/// Segments for `geometry::calculate_linear`: straight half-links between
+/// neighbours, zero-length lines at strand ends.
+pub fn generate_linear(bases: &[Base], pt: &PairTable) -> Vec<Vec<Segment>> {
+    (0..bases.len())
+        .map(|i| vec![linear_incoming(i, bases, pt), linear_outgoing(i, bases, pt)])
+        .collect()
+}
+
+/// Straight incoming half-link for base i, or a zero-length stub at a strand start.
+fn linear_incoming(i: usize, bases: &[Base], pt: &PairTable) -> Segment {
+    if pt.nicks.contains(&i) {
+        zero_line(bases[i].x, bases[i].y)
+    } else {
+        half_line(&bases[i], &bases[i - 1])
+    }
+}
+
+/// Straight outgoing half-link for base i, or a zero-length stub at a strand end.
+fn linear_outgoing(i: usize, bases: &[Base], pt: &PairTable) -> Segment {
+    if i + 1 == bases.len() || pt.nicks.contains(&(i + 1)) {
+        zero_line(bases[i].x, bases[i].y)
+    } else {
+        half_line(&bases[i], &bases[i + 1])
+    }
+}
+
+/// Line from base `b` to the midpoint between `b` and `other`.
+fn half_line(b: &Base, other: &Base) -> Segment {
+    Segment::Line(LineSegment {
+        x: b.x,
+        y: b.y,
+        x1: (b.x + other.x) / 2.0,
+        y1: (b.y + other.y) / 2.0,
+    })
+}
+
+/// Layout shared by the segments of every base.
+struct LoopContext<'a> {
+    bases: &'a [Base],
+    loops: &'a [Loop],
+    infos: &'a [LoopInfo],
+    pt: &'a PairTable,
+    opts: &'a LayoutOptions,
+    nicks: std::collections::HashSet<usize>,
+}
+
+impl LoopContext<'_> {
+    /// Incoming segment for base i (from base i-1 side).
+    fn incoming(&self, i: usize) -> Segment {
+        let (bases, loops) = (self.bases, self.loops);
+        let n = bases.len();
+        // Nick at position i means break between base i-1 and base i
+        if self.nicks.contains(&i) {
+            let shared = bases[i].loop1;
+            if self.uses_arcs(shared) {
+                // Degenerate ARC at base's own angle
+                let lp = &loops[shared];
+                let a = bases[i].angle1;
+                return Segment::Arc(ArcSegment {
+                    ellipse: lp.ellipse,
+                    x: lp.x,
+                    y: lp.y,
+                    r: lp.radius,
+                    t1: a,
+                    t2: a,
+                });
+            } else {
+                return zero_line(bases[i].x, bases[i].y);
+            }
+        }
+
+        let j = if i == 0 { n - 1 } else { i - 1 };
         let shared = bases[i].loop1;
-        if loop_has_unpaired(infos, shared) {
-            // Degenerate ARC at base's own angle
+
+        if self.uses_arcs(shared) && !self.is_straight_link(shared, i, j) {
+            // ARC: t1 = base_i angle, t2 = midpoint angle
             let lp = &loops[shared];
-            let a = bases[i].angle1;
-            return Segment::Arc(ArcSegment {
+            let angle_i = bases[i].angle1;
+            let angle_j = bases[j].angle2;
+            let mid = (angle_i + angle_j) / 2.0;
+            Segment::Arc(ArcSegment {
+                ellipse: lp.ellipse,
                 x: lp.x,
                 y: lp.y,
                 r: lp.radius,
-                t1: a,
-                t2: a,
-            });
+                t1: angle_i,
+                t2: mid,
+            })
         } else {
-            return zero_line(bases[i].x, bases[i].y);
+            // LINE: from base i to midpoint of bases i and j
+            let mx = (bases[i].x + bases[j].x) / 2.0;
+            let my = (bases[i].y + bases[j].y) / 2.0;
+            Segment::Line(LineSegment {
+                x: bases[i].x,
+                y: bases[i].y,
+                x1: mx,
+                y1: my,
+            })
         }
     }
 
-    let j = if i == 0 { n - 1 } else { i - 1 };
-    let shared = bases[i].loop1;
-
-    if loop_has_unpaired(infos, shared) {
-        // ARC: t1 = base_i angle, t2 = midpoint angle
-        let lp = &loops[shared];
-        let angle_i = bases[i].angle1;
-        let angle_j = bases[j].angle2;
-        let mid = (angle_i + angle_j) / 2.0;
-        Segment::Arc(ArcSegment {
-            x: lp.x,
-            y: lp.y,
-            r: lp.radius,
-            t1: angle_i,
-            t2: mid,
-        })
-    } else {
-        // LINE: from base i to midpoint of bases i and j
-        let mx = (bases[i].x + bases[j].x) / 2.0;
-        let my = (bases[i].y + bases[j].y) / 2.0;
-        Segment::Line(LineSegment {
-            x: bases[i].x,
-            y: bases[i].y,
-            x1: mx,
-            y1: my,
-        })
-    }
-}
+    /// Outgoing segment for base i (to base i+1 side).
+    fn outgoing(&self, i: usize) -> Segment {
+        let (bases, loops) = (self.bases, self.loops);
+        let n = bases.len();
+        let next_pos = (i + 1) % n;
+        if self.nicks.contains(&next_pos) {
+            let shared = bases[i].loop2;
+            if self.uses_arcs(shared) {
+                // Degenerate ARC at midpoint of base_i and base_next angles
+                let lp = &loops[shared];
+                let angle_i = bases[i].angle2;
+                let angle_j = bases[next_pos].angle1;
+                let mid = (angle_i + angle_j) / 2.0;
+                return Segment::Arc(ArcSegment {
+                    ellipse: lp.ellipse,
+                    x: lp.x,
+                    y: lp.y,
+                    r: lp.radius,
+                    t1: mid,
+                    t2: mid,
+                });
+            } else {
+                return zero_line(bases[i].x, bases[i].y);
+            }
+        }
 
-/// Outgoing segment for base i (to base i+1 side).
-fn compute_outgoing(
-    i: usize,
-    n: usize,
-    bases: &[Base],
-    loops: &[Loop],
-    infos: &[LoopInfo],
-    nicks: &std::collections::HashSet<usize>,
-) -> Segment {
-    let next_pos = (i + 1) % n;
-    if nicks.contains(&next_pos) {
+        let j = next_pos;
         let shared = bases[i].loop2;
-        if loop_has_unpaired(infos, shared) {
-            // Degenerate ARC at midpoint of base_i and base_next angles
+
+        if self.uses_arcs(shared) && !self.is_straight_link(shared, i, j) {
+            // ARC: t1 = midpoint angle, t2 = base_i angle
             let lp = &loops[shared];
             let angle_i = bases[i].angle2;
-            let angle_j = bases[next_pos].angle1;
+            let angle_j = bases[j].angle1;
             let mid = (angle_i + angle_j) / 2.0;
-            return Segment::Arc(ArcSegment {
+            Segment::Arc(ArcSegment {
+                ellipse: lp.ellipse,
                 x: lp.x,
                 y: lp.y,
                 r: lp.radius,
                 t1: mid,
-                t2: mid,
-            });
+                t2: angle_i,
+            })
         } else {
-            return zero_line(bases[i].x, bases[i].y);
+            // LINE: from base i to midpoint of bases i and j
+            let mx = (bases[i].x + bases[j].x) / 2.0;
+            let my = (bases[i].y + bases[j].y) / 2.0;
+            Segment::Line(LineSegment {
+                x: bases[i].x,
+                y: bases[i].y,
+                x1: mx,
+                y1: my,
+            })
         }
     }
 
-    let j = next_pos;
-    let shared = bases[i].loop2;
-
-    if loop_has_unpaired(infos, shared) {
-        // ARC: t1 = midpoint angle, t2 = base_i angle
-        let lp = &loops[shared];
-        let angle_i = bases[i].angle2;
-        let angle_j = bases[j].angle1;
-        let mid = (angle_i + angle_j) / 2.0;
-        Segment::Arc(ArcSegment {
-            x: lp.x,
-            y: lp.y,
-            r: lp.radius,
-            t1: mid,
-            t2: angle_i,
-        })
-    } else {
-        // LINE: from base i to midpoint of bases i and j
-        let mx = (bases[i].x + bases[j].x) / 2.0;
-        let my = (bases[i].y + bases[j].y) / 2.0;
-        Segment::Line(LineSegment {
-            x: bases[i].x,
-            y: bases[i].y,
-            x1: mx,
-            y1: my,
-        })
+    /// Whether links on loop `li` are drawn as arcs under the selected segment mode.
+    fn uses_arcs(&self, li: usize) -> bool {
+        match self.opts.segment_mode {
+            SegmentMode::Auto => loop_has_unpaired(self.infos, li),
+            SegmentMode::AllArcs => true,
+            SegmentMode::AllLines => false,
+        }
+    }
+
+    /// The empty side of a straightened bulge (two paired bases) is drawn as a line.
+    fn is_straight_link(&self, li: usize, a: usize, b: usize) -> bool {
+        let (opts, pairs) = (self.opts, &self.pt.pairs);
+        opts.segment_mode == SegmentMode::Auto
+            && opts.straight_bulges
+            && self.loops[li].kind == LoopKind::Bulge
+            && self.infos[li].nicks_in_loop.is_empty()
+            && pairs[a] != a
+            && pairs[b] != b
     }
 }
 
@@ -1,11 +1,24 @@
 use crate::types::*;
 use serde::Deserialize;
+use std::borrow::Cow;
 use std::f64::consts::PI;
 use std::fmt::Write;
 
+/// Distance (layout units) from a paired base to its label, matching the offset
+/// the layout uses for unpaired `xt`/`yt`.
+const LABEL_OFFSET: f64 = 0.5;
+
+/// Height reserved below the structure for `sequence_track`, in pixels.
+const TRACK_HEIGHT: f64 = 40.0;
+
 /// Default nucleotide-type colors: [A, U, G, C]
 pub const DEFAULT_NUCLEOTIDE_COLORS: [&str; 4] = ["green", "red", "black", "blue"];
 
+/// Colorblind-safe nucleotide-type colors from the Okabe–Ito palette: [A, U, G, C]
+///
+/// Bluish green and vermillion stay distinct under red–green color blindness.
+pub const CB_SAFE_NUCLEOTIDE_COLORS: [&str; 4] = ["#009E73", "#D55E00", "#000000", "#0072B2"];
+
 /// Equilibrium probability colormap (dark purple → blue → cyan → green → yellow → red → dark red).
 /// 11 stops evenly spaced from 0.0 to 1.0.
 const PROB_COLORMAP: [(f64, f64, f64); 11] = [
@@ -22,18 +35,167 @@ const PROB_COLORMAP: [(f64, f64, f64); 11] = [
     (0.55, 0.01, 0.01), // 1.0  dark red
 ];
 
+const VIRIDIS_COLORMAP: [(f64, f64, f64); 11] = [
+    (0.267, 0.005, 0.329),
+    (0.283, 0.141, 0.458),
+    (0.254, 0.265, 0.530),
+    (0.207, 0.372, 0.553),
+    (0.164, 0.471, 0.558),
+    (0.128, 0.567, 0.551),
+    (0.135, 0.659, 0.518),
+    (0.267, 0.749, 0.441),
+    (0.478, 0.821, 0.317),
+    (0.741, 0.873, 0.150),
+    (0.993, 0.906, 0.144),
+];
+
+const PLASMA_COLORMAP: [(f64, f64, f64); 11] = [
+    (0.050, 0.030, 0.528),
+    (0.254, 0.014, 0.615),
+    (0.417, 0.001, 0.658),
+    (0.562, 0.051, 0.642),
+    (0.692, 0.165, 0.564),
+    (0.798, 0.280, 0.470),
+    (0.881, 0.392, 0.383),
+    (0.949, 0.517, 0.295),
+    (0.988, 0.652, 0.212),
+    (0.988, 0.809, 0.145),
+    (0.940, 0.975, 0.131),
+];
+
+/// Light gray → black, so low values stay visible on a white background.
+const GRAYSCALE_COLORMAP: [(f64, f64, f64); 11] = [
+    (0.90, 0.90, 0.90),
+    (0.81, 0.81, 0.81),
+    (0.72, 0.72, 0.72),
+    (0.63, 0.63, 0.63),
+    (0.54, 0.54, 0.54),
+    (0.45, 0.45, 0.45),
+    (0.36, 0.36, 0.36),
+    (0.27, 0.27, 0.27),
+    (0.18, 0.18, 0.18),
+    (0.09, 0.09, 0.09),
+    (0.00, 0.00, 0.00),
+];
+
+/// Blue → neutral gray (0.5) → red.
+const COOLWARM_COLORMAP: [(f64, f64, f64); 11] = [
+    (0.230, 0.299, 0.754),
+    (0.353, 0.472, 0.890),
+    (0.484, 0.624, 0.975),
+    (0.616, 0.741, 0.999),
+    (0.736, 0.818, 0.953),
+    (0.865, 0.865, 0.865),
+    (0.958, 0.796, 0.718),
+    (0.968, 0.688, 0.567),
+    (0.930, 0.536, 0.420),
+    (0.848, 0.352, 0.282),
+    (0.706, 0.016, 0.150),
+];
+
+/// Built-in colormap for probability and position coloring.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Colormap {
+    /// Equilibrium probability colormap (dark purple → dark red)
+    #[default]
+    Equilibrium,
+    Viridis,
+    Plasma,
+    /// Light gray → black
+    Grayscale,
+    /// Diverging blue → gray → red
+    Coolwarm,
+}
+
+impl Colormap {
+    /// 11 stops evenly spaced from 0.0 to 1.0.
+    fn stops(self) -> &'static [(f64, f64, f64); 11] {
+        match self {
+            Colormap::Equilibrium => &PROB_COLORMAP,
+            Colormap::Viridis => &VIRIDIS_COLORMAP,
+            Colormap::Plasma => &PLASMA_COLORMAP,
+            Colormap::Grayscale => &GRAYSCALE_COLORMAP,
+            Colormap::Coolwarm => &COOLWARM_COLORMAP,
+        }
+    }
+
+    /// Convert a value (0.0–1.0) to an RGB hex color by linear interpolation.
+    pub fn color(self, p: f64) -> String {
+        let stops = self.stops();
+        let p = p.clamp(0.0, 1.0);
+        let t = p * 10.0;
+        let i = (t as usize).min(9);
+        let frac = t - i as f64;
+        let (r0, g0, b0) = stops[i];
+        let (r1, g1, b1) = stops[i + 1];
+        let r = ((r0 + (r1 - r0) * frac) * 255.0) as u8;
+        let g = ((g0 + (g1 - g0) * frac) * 255.0) as u8;
+        let b = ((b0 + (b1 - b0) * frac) * 255.0) as u8;
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// Position (0.0–1.0) of `v` on a colormap spanning `lo..=hi`. Diverging ranges put
+/// zero at 0.5 and scale each sign by its own bound.
+fn value_fraction(v: f64, lo: f64, hi: f64, diverging: bool) -> f64 {
+    if diverging {
+        if v == 0.0 {
+            0.5
+        } else if v < 0.0 {
+            0.5 - 0.5 * v / lo
+        } else {
+            0.5 + 0.5 * v / hi
+        }
+    } else if hi > lo {
+        (v - lo) / (hi - lo)
+    } else {
+        0.5
+    }
+}
+
 /// Convert an equilibrium probability (0.0–1.0) to an RGB hex color.
 pub fn probability_to_color(p: f64) -> String {
-    let p = p.clamp(0.0, 1.0);
-    let t = p * 10.0;
-    let i = (t as usize).min(9);
-    let frac = t - i as f64;
-    let (r0, g0, b0) = PROB_COLORMAP[i];
-    let (r1, g1, b1) = PROB_COLORMAP[i + 1];
-    let r = ((r0 + (r1 - r0) * frac) * 255.0) as u8;
-    let g = ((g0 + (g1 - g0) * frac) * 255.0) as u8;
-    let b = ((b0 + (b1 - b0) * frac) * 255.0) as u8;
-    format!("#{r:02x}{g:02x}{b:02x}")
+    Colormap::Equilibrium.color(p)
+}
+
+/// Black-on-white hatch patterns standing in for nucleotide colors in monochrome mode:
+/// (id, pattern body) for [A, U, G, C].
+const MONO_PATTERNS: [(&str, &str); 4] = [
+    (
+        "mono-a",
+        r#"<path d="M0 4 4 0" stroke="black" stroke-width="1"/>"#,
+    ),
+    (
+        "mono-u",
+        r#"<path d="M0 2 4 2" stroke="black" stroke-width="1"/>"#,
+    ),
+    (
+        "mono-g",
+        r#"<path d="M0 4 4 0M0 0 4 4" stroke="black" stroke-width="0.8"/>"#,
+    ),
+    ("mono-c", r#"<circle cx="2" cy="2" r="0.9" fill="black"/>"#),
+];
+
+/// Built-in nucleotide-type palette.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NucleotidePalette {
+    /// `DEFAULT_NUCLEOTIDE_COLORS`
+    #[default]
+    Default,
+    /// `CB_SAFE_NUCLEOTIDE_COLORS` (Okabe–Ito)
+    ColorblindSafe,
+}
+
+impl NucleotidePalette {
+    /// Colors in `base_colors` order: [A, U, G, C]
+    pub fn colors(self) -> [&'static str; 4] {
+        match self {
+            NucleotidePalette::Default => DEFAULT_NUCLEOTIDE_COLORS,
+            NucleotidePalette::ColorblindSafe => CB_SAFE_NUCLEOTIDE_COLORS,
+        }
+    }
 }
 
 /// Legend type to render alongside the structure.
@@ -46,6 +208,58 @@ pub enum Legend {
     Nucleotide,
     /// Gradient colorbar for equilibrium probability (0.0–1.0)
     Probability,
+    /// Gradient colorbar for sequence position (5′ → 3′)
+    Position,
+}
+
+/// Range of the values in `SvgOptions::probabilities`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbScale {
+    /// Fractions in 0.0–1.0
+    #[default]
+    Unit,
+    /// Percentages in 0–100
+    Percent,
+}
+
+impl ProbScale {
+    /// Value corresponding to probability 1.0.
+    fn max(self) -> f64 {
+        match self {
+            Self::Unit => 1.0,
+            Self::Percent => 100.0,
+        }
+    }
+}
+
+/// How a base pair bond is drawn.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PairStyle {
+    /// Line between the paired bases
+    #[default]
+    Line,
+    /// Filled dot at the pair midpoint only
+    MidpointDot,
+    /// Line plus a filled dot at the midpoint
+    LineWithDot,
+    /// Short stub from each base toward its partner, leaving the middle open
+    /// (see `pair_tick_len`)
+    Tick,
+}
+
+/// Shape of a base marker, sized from `base_radius`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BaseShape {
+    /// Circle of radius `base_radius`
+    #[default]
+    Circle,
+    /// Axis-aligned square with side `2 * base_radius`
+    Square,
+    /// Square rotated 45°, with corners `base_radius` from the center
+    Diamond,
 }
 
 /// Options controlling SVG rendering appearance.
@@ -53,13 +267,30 @@ pub enum Legend {
 /// Defaults match reference web frontend style at scale=50:
 /// - base_unit = scale * 0.05 = 2.5
 /// - All widths/radii are integer multiples of base_unit
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 #[serde(default)]
 pub struct SvgOptions {
     /// Pixels per geometry unit (default: 50.0)
     pub scale: f64,
+    /// Derive backbone_width, pair_width, pair_tick_len, base_radius and
+    /// base_stroke_width from `scale` as their base_unit multiples, ignoring the set values (default: false)
+    pub auto_stroke: bool,
     /// ViewBox padding in pixels (default: 20.0)
     pub padding: f64,
+    /// Per-side viewBox padding in pixels as [top, right, bottom, left], overriding
+    /// `padding` (default: None). The legend stays outside the right padding.
+    pub padding_sides: Option<[f64; 4]>,
+    /// Grow the shorter side of the padded structure region so it is square,
+    /// keeping the structure centered (default: false). The legend and sequence
+    /// track are added outside the square.
+    pub square: bool,
+    /// `preserveAspectRatio` attribute for the `<svg>` element, e.g. `"none"` or
+    /// `"xMinYMin slice"` (default: None, omitted so viewers use `xMidYMid meet`)
+    pub preserve_aspect_ratio: Option<String>,
+    /// Mark the `<svg>` element as an image for screen readers with `role="img"`
+    /// and this `aria-label` (default: None). An empty label falls back to
+    /// "RNA secondary structure diagram".
+    pub aria_label: Option<String>,
     /// Backbone stroke width — 2× base_unit (default: 5.0)
     pub backbone_width: f64,
     /// Backbone stroke color (default: "black")
@@ -68,137 +299,793 @@ pub struct SvgOptions {
     pub pair_width: f64,
     /// Pair bond stroke color (default: "black")
     pub pair_color: String,
+    /// Color for bonds between bases on different strands, to show which contacts
+    /// hold a complex together (default: None, uses pair_color)
+    pub interstrand_pair_color: Option<String>,
+    /// Pair bonds to draw prominently, as (i, j) base indices in either order
+    /// (default: None, all bonds alike). Bonds not listed are drawn faded, e.g. to
+    /// highlight conserved helices.
+    pub emphasized_pairs: Option<Vec<(usize, usize)>>,
+    /// Pair bond drawing style (default: Line). Midpoint dots have radius 2× pair_width
+    /// and use pair_color.
+    pub pair_style: PairStyle,
+    /// Length of each `PairStyle::Tick` stub in pixels, measured from the base
+    /// center and capped at half the bond — 6× base_unit (default: 15.0)
+    pub pair_tick_len: f64,
     /// Base marker circle radius — 3× base_unit (default: 7.5)
     pub base_radius: f64,
+    /// Base marker shape (default: Circle)
+    pub base_shape: BaseShape,
+    /// Per-base marker shapes, e.g. to encode base categories (default: None).
+    /// Bases beyond the list use `base_shape`.
+    pub per_base_shape: Option<Vec<BaseShape>>,
     /// Base marker fill color (default: "#900c00")
     pub base_fill: String,
     /// Base marker stroke width — 1× base_unit (default: 2.5)
     pub base_stroke_width: f64,
+    /// Whether to draw base marker circles (default: true). When false only the
+    /// backbone and pair bonds are drawn, and labels sit on the backbone at each base.
+    pub show_bases: bool,
     /// Whether to show nucleotide labels (default: false)
     pub show_labels: bool,
     /// Font size for labels in pixels (default: 10.0)
     pub font_size: f64,
+    /// Label text color (default: "black")
+    pub label_color: String,
+    /// Halo color drawn behind label glyphs for contrast on dark fills (default: None).
+    /// The halo stroke is a quarter of font_size wide.
+    pub label_outline: Option<String>,
     /// Per-nucleotide-type colors: [A, U, G, C] (default: None, uses base_fill for all)
     pub base_colors: Option<[String; 4]>,
+    /// Built-in palette to color bases by nucleotide type when `base_colors` is
+    /// unset (default: None). Also used by the nucleotide legend.
+    pub nucleotide_palette: Option<NucleotidePalette>,
+    /// Fill for non-A/U/T/G/C characters (e.g. IUPAC `N`, `R`, `Y`) when base_colors
+    /// is set (default: None, uses base_fill)
+    pub ambiguous_color: Option<String>,
     /// Per-base colors (e.g. for probability coloring). Takes priority over base_colors and base_fill.
     pub per_base_colors: Option<Vec<String>>,
+    /// Per-base marker stroke (ring) colors for a second metric alongside the fill
+    /// (default: None, ring matches the fill). Bases beyond the list keep the default.
+    pub per_base_ring_colors: Option<Vec<String>>,
+    /// Per-base marker opacity, applied to both fill and ring (default: None).
+    /// Values are clamped to 0.0–1.0; bases beyond the list stay opaque.
+    pub per_base_opacity: Option<Vec<f64>>,
     /// Per-base equilibrium probabilities (0.0–1.0). Converted to per_base_colors via
     /// `probability_to_color()` and sets legend to Probability automatically.
-    /// Takes priority over per_base_colors if both are set.
+    /// Takes priority over per_base_colors if both are set, unless
+    /// `per_base_override` is enabled.
     pub probabilities: Option<Vec<f64>>,
+    /// Let non-empty `per_base_colors` entries override the probability-derived
+    /// color of their base, e.g. to gray out positions without data (default: false).
+    /// Empty entries and bases beyond the list keep the probability color.
+    pub per_base_override: bool,
+    /// Range of `probabilities` values, also used for the legend ticks (default: Unit)
+    pub probability_scale: ProbScale,
+    /// Treat `probabilities` as signed data (e.g. Δreactivity) and color it through the
+    /// Coolwarm colormap with the neutral gray at zero (default: false). Negative and
+    /// positive values are scaled separately, so zero stays neutral for uneven ranges.
+    pub diverging: bool,
+    /// `(min, max)` of the `probabilities` values, replacing `probability_scale` for
+    /// coloring and legend ticks (default: None). With `diverging` and no range, the
+    /// data extent widened to include zero is used.
+    pub value_range: Option<(f64, f64)>,
     /// Whether to show 3' direction arrows at strand ends (default: true)
     pub show_arrows: bool,
+    /// Draw each arrowhead as its own `<path>` instead of a shared `marker-end`
+    /// (default: false). Helps SVG-to-PDF converters that mishandle markers, and
+    /// lets arrows follow `strand_colors`.
+    pub arrows_as_paths: bool,
     /// Whether to auto-rotate so the primary stem is vertical (default: true)
     pub align_stem: bool,
     /// Legend to render alongside the structure (default: None)
     pub legend: Legend,
+    /// Legend title (default: None): replaces the rotated label of the probability
+    /// and position colorbars (e.g. "SHAPE reactivity" when `probabilities` holds
+    /// another per-base metric) and is shown as a heading above the nucleotide legend
+    pub legend_title: Option<String>,
+    /// Color bases by sequence position (5′ → 3′) through `colormap`
+    /// and set legend to Position (default: false). Ignored when `probabilities` is set.
+    pub position_gradient: bool,
+    /// Whether to add a `<title>` tooltip (1-based index and nucleotide) to each base
+    /// marker (default: false)
+    pub interactive: bool,
+    /// Added to the 1-based base number wherever numbers are shown (tooltips and
+    /// `sequence_track` ticks), e.g. 500 so an excerpt starts at 501 (default: 0)
+    pub number_offset: usize,
+    /// Loop geometry options used when laying out the structure
+    pub layout: LayoutOptions,
+    /// Round every emitted coordinate to the nearest 0.5 px for crisper thin strokes
+    /// at small scales (default: false)
+    pub snap_pixels: bool,
+    /// Colormap for `probabilities` and `position_gradient` (default: Equilibrium)
+    pub colormap: Colormap,
+    /// Print-safe mode (default: false): black strokes and white base fills, ignoring
+    /// color settings. Probability/position data use the Grayscale colormap and
+    /// nucleotide types use hatch patterns (also shown in the legend).
+    pub monochrome: bool,
+    /// Backbone color per strand, in strand order (default: None). Strands beyond
+    /// the list fall back to `backbone_color`.
+    pub strand_colors: Option<Vec<String>>,
+    /// Draw each strand's backbone as one `<path>` with round joins instead of a
+    /// separate element per half-segment (default: false). Avoids tiny notches
+    /// where straight pieces meet at high zoom.
+    pub merge_backbone: bool,
+    /// Tag base markers with `data-index` and pair bonds with `data-i`/`data-j`
+    /// (0-based base indices) for selecting elements from scripts (default: false)
+    pub data_attrs: bool,
+    /// Draw the sequence as a linear 5′ → 3′ track of colored cells below the
+    /// structure, with ticks every 10 bases (default: false). Cells use the same
+    /// fills as the base markers.
+    pub sequence_track: bool,
+    /// Only draw bases `a..=b` (0-based) of the full layout, framed to just those
+    /// bases (default: None). Pair bonds leaving the window are drawn faded and
+    /// clipped at the frame edge.
+    pub window: Option<(usize, usize)>,
+    /// Zoom in on bases `a..=b` (0-based): the viewBox bounds just those bases
+    /// (plus padding) while the whole structure is still drawn, with the other base
+    /// markers faded, as context (default: None). Takes precedence over `window`
+    /// for framing.
+    pub focus: Option<(usize, usize)>,
+    /// Embed the structure notation and sequence in a `<metadata>` element so
+    /// scripts can recover what was drawn (default: false). `render` writes the
+    /// structure back out from the pair table; `draw_svg` keeps the input as given.
+    pub embed_source: bool,
+    /// Draw faint gridlines every this many layout units behind the structure,
+    /// with the layout origin's axes drawn darker, for checking coordinates
+    /// (default: None)
+    pub debug_grid: Option<f64>,
+    /// Decimal places written for coordinates and path data (default: 2)
+    pub coord_precision: usize,
+    /// Backbone arcs sweeping less than this many radians are drawn as a straight
+    /// `<line>` between their endpoints, avoiding sliver-arc artifacts in some
+    /// renderers (default: 0.0, arcs always drawn as arcs)
+    pub min_arc_sweep: f64,
+    /// Wrap each layer in its own `<g id="…">` (grid, bonds, backbone, arrows,
+    /// bases, labels, sequence-track, legend) for editing in vector tools
+    /// (default: false)
+    pub group_layers: bool,
 }
 
 impl Default for SvgOptions {
     fn default() -> Self {
         Self {
             scale: 50.0,
+            auto_stroke: false,
             padding: 20.0,
+            padding_sides: None,
+            square: false,
+            preserve_aspect_ratio: None,
+            aria_label: None,
             backbone_width: 5.0,
             backbone_color: "black".into(),
             pair_width: 2.5,
             pair_color: "black".into(),
+            interstrand_pair_color: None,
+            emphasized_pairs: None,
+            pair_style: PairStyle::Line,
+            pair_tick_len: 15.0,
             base_radius: 7.5,
+            base_shape: BaseShape::Circle,
+            per_base_shape: None,
             base_fill: "#900c00".into(),
             base_stroke_width: 2.5,
+            show_bases: true,
             show_labels: false,
             font_size: 10.0,
+            label_color: "black".into(),
+            label_outline: None,
             base_colors: None,
+            nucleotide_palette: None,
+            ambiguous_color: None,
             per_base_colors: None,
+            per_base_ring_colors: None,
+            per_base_opacity: None,
             probabilities: None,
+            per_base_override: false,
+            probability_scale: ProbScale::Unit,
+            diverging: false,
+            value_range: None,
             show_arrows: true,
+            arrows_as_paths: false,
             align_stem: true,
             legend: Legend::None,
+            legend_title: None,
+            position_gradient: false,
+            interactive: false,
+            number_offset: 0,
+            layout: LayoutOptions::default(),
+            snap_pixels: false,
+            colormap: Colormap::Equilibrium,
+            monochrome: false,
+            strand_colors: None,
+            merge_backbone: false,
+            data_attrs: false,
+            sequence_track: false,
+            window: None,
+            focus: None,
+            embed_source: false,
+            debug_grid: None,
+            coord_precision: 2,
+            min_arc_sweep: 0.0,
+            group_layers: false,
         }
     }
 }
 
 impl SvgOptions {
+    fn in_window(&self, i: usize) -> bool {
+        self.window.is_none_or(|(a, b)| a <= i && i <= b)
+    }
+
+    /// Fade the markers of bases outside `focus` through `per_base_opacity`.
+    fn resolve_focus(&self, (a, b): (usize, usize), n: usize) -> SvgOptions {
+        let opacity = (0..n)
+            .map(|i| {
+                let own = self
+                    .per_base_opacity
+                    .as_ref()
+                    .and_then(|o| o.get(i))
+                    .copied()
+                    .filter(|o| !o.is_nan())
+                    .unwrap_or(1.0);
+                if a <= i && i <= b { own } else { own * 0.3 }
+            })
+            .collect();
+        SvgOptions {
+            per_base_opacity: Some(opacity),
+            ..self.clone()
+        }
+    }
+
+    /// Replace all color settings with their monochrome equivalents.
+    fn resolve_monochrome(&self) -> SvgOptions {
+        let patterns = (self.base_colors.is_some() || self.legend == Legend::Nucleotide)
+            .then(|| MONO_PATTERNS.map(|(id, _)| format!("url(#{id})")));
+        SvgOptions {
+            backbone_color: "black".into(),
+            pair_color: "black".into(),
+            interstrand_pair_color: None,
+            base_fill: "white".into(),
+            label_color: "black".into(),
+            label_outline: self.label_outline.as_ref().map(|_| "white".into()),
+            base_colors: patterns,
+            ambiguous_color: None,
+            per_base_colors: None,
+            per_base_ring_colors: None,
+            strand_colors: None,
+            colormap: Colormap::Grayscale,
+            ..self.clone()
+        }
+    }
+
+    /// Recompute stroke widths and marker radius from `scale`.
+    fn resolve_auto_stroke(&self) -> SvgOptions {
+        let unit = self.scale * 0.05;
+        SvgOptions {
+            backbone_width: 2.0 * unit,
+            pair_width: unit,
+            base_radius: 3.0 * unit,
+            base_stroke_width: unit,
+            pair_tick_len: 6.0 * unit,
+            auto_stroke: false,
+            ..self.clone()
+        }
+    }
+
     /// Convert `probabilities` into `per_base_colors` and set legend to Probability.
+    /// Records the effective `value_range` so the legend can label signed data.
     fn resolve_probabilities(&self) -> SvgOptions {
-        let colors = self
-            .probabilities
-            .as_ref()
-            .map(|ps| ps.iter().map(|&p| probability_to_color(p)).collect());
+        let manual = self
+            .per_base_colors
+            .as_deref()
+            .filter(|_| self.per_base_override)
+            .unwrap_or_default();
+        let range = self.value_range.or_else(|| {
+            let ps = self.probabilities.as_deref().filter(|_| self.diverging)?;
+            let lo = ps.iter().copied().fold(0.0, f64::min);
+            let hi = ps.iter().copied().fold(0.0, f64::max);
+            Some((lo, hi))
+        });
+        // Monochrome keeps its grayscale ramp
+        let colormap = if self.diverging && !self.monochrome {
+            Colormap::Coolwarm
+        } else {
+            self.colormap
+        };
+        let colors = self.probabilities.as_ref().map(|ps| {
+            let max = self.probability_scale.max();
+            ps.iter()
+                .enumerate()
+                .map(|(i, &p)| match manual.get(i) {
+                    Some(c) if !c.is_empty() => c.clone(),
+                    _ => colormap.color(match range {
+                        Some((lo, hi)) => value_fraction(p, lo, hi, self.diverging),
+                        None => p / max,
+                    }),
+                })
+                .collect()
+        });
         SvgOptions {
-            scale: self.scale,
-            padding: self.padding,
-            backbone_width: self.backbone_width,
-            backbone_color: self.backbone_color.clone(),
-            pair_width: self.pair_width,
-            pair_color: self.pair_color.clone(),
-            base_radius: self.base_radius,
-            base_fill: self.base_fill.clone(),
-            base_stroke_width: self.base_stroke_width,
-            show_labels: self.show_labels,
-            font_size: self.font_size,
-            base_colors: self.base_colors.clone(),
             per_base_colors: colors,
             probabilities: None,
-            show_arrows: self.show_arrows,
-            align_stem: self.align_stem,
             legend: Legend::Probability,
+            colormap,
+            value_range: range,
+            ..self.clone()
+        }
+    }
+
+    /// Convert base positions into `per_base_colors` and set legend to Position.
+    fn resolve_position_gradient(&self, n: usize) -> SvgOptions {
+        let denom = n.saturating_sub(1).max(1) as f64;
+        let colors = (0..n)
+            .map(|i| self.colormap.color(i as f64 / denom))
+            .collect();
+        SvgOptions {
+            per_base_colors: Some(colors),
+            position_gradient: false,
+            legend: Legend::Position,
+            ..self.clone()
         }
     }
 }
 
 /// Render a DrawResult as an SVG string.
+///
+/// `seq`, if given, holds one character per base; `+`/`&` strand breaks in it are
+/// skipped.
 pub fn render(result: &DrawResult, seq: Option<&str>, opts: &SvgOptions) -> String {
-    // If probabilities are provided, convert to per_base_colors and set legend
-    let resolved;
-    let opts = if opts.probabilities.is_some() {
-        resolved = opts.resolve_probabilities();
-        &resolved
+    render_svg(result, seq, None, opts)
+}
+
+/// Like `render`, recording `source` verbatim as the structure notation when
+/// `embed_source` is set.
+pub fn render_with_source(
+    result: &DrawResult,
+    seq: Option<&str>,
+    source: &str,
+    opts: &SvgOptions,
+) -> String {
+    render_svg(result, seq, Some(source), opts)
+}
+
+fn render_svg(
+    result: &DrawResult,
+    seq: Option<&str>,
+    source: Option<&str>,
+    opts: &SvgOptions,
+) -> String {
+    let mut svg = String::with_capacity(4096);
+    let _ = render_svg_to(&mut svg, result, seq, source, opts);
+    svg
+}
+
+/// Like `render`, writing the SVG into `w` as it is generated instead of
+/// building a String. Stops at and returns the first write error.
+pub fn render_to<W: Write>(
+    w: &mut W,
+    result: &DrawResult,
+    seq: Option<&str>,
+    opts: &SvgOptions,
+) -> std::fmt::Result {
+    render_svg_to(w, result, seq, None, opts)
+}
+
+/// Like `render_to`, recording `source` as in `render_with_source`.
+pub fn render_with_source_to<W: Write>(
+    w: &mut W,
+    result: &DrawResult,
+    seq: Option<&str>,
+    source: &str,
+    opts: &SvgOptions,
+) -> std::fmt::Result {
+    render_svg_to(w, result, seq, Some(source), opts)
+}
+
+fn render_svg_to(
+    w: &mut dyn Write,
+    result: &DrawResult,
+    seq: Option<&str>,
+    source: Option<&str>,
+    opts: &SvgOptions,
+) -> std::fmt::Result {
+    let opts = resolve_options(opts, result.layout.bases.len());
+    let frame = compute_frame(result, &opts);
+    let mut svg = ErrorLatch {
+        inner: w,
+        result: Ok(()),
+    };
+
+    write_svg_open(
+        &mut svg,
+        frame.vb_x,
+        frame.vb_y,
+        frame.struct_w + frame.legend_w,
+        frame.vb_h,
+        &opts,
+    );
+    if opts.embed_source {
+        write_metadata(&mut svg, result, seq, source);
+    }
+    write_body(&mut svg, result, seq, &frame, &opts);
+
+    // 6. Legend (rightmost)
+    if opts.legend != Legend::None {
+        let legend_x = frame.vb_x + frame.struct_w;
+        render_legend(&mut svg, legend_x, frame.vb_y, frame.vb_h, &opts);
+    }
+
+    let _ = svg.write_str("</svg>");
+    svg.result
+}
+
+/// Forwards writes to `inner` until the first error, which it keeps so the
+/// render helpers can ignore write results.
+struct ErrorLatch<'a> {
+    inner: &'a mut dyn Write,
+    result: std::fmt::Result,
+}
+
+impl Write for ErrorLatch<'_> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        if self.result.is_ok() {
+            self.result = self.inner.write_str(s);
+        }
+        self.result
+    }
+}
+
+/// Like `render`, also returning the SVG-space center of each base marker, in base
+/// order, for hit-testing (e.g. click-to-select) without parsing the SVG.
+pub fn render_with_map(
+    result: &DrawResult,
+    seq: Option<&str>,
+    opts: &SvgOptions,
+) -> (String, Vec<(f64, f64)>) {
+    let centers = result
+        .layout
+        .bases
+        .iter()
+        .map(|b| snap(b.x * opts.scale, -b.y * opts.scale, opts))
+        .collect();
+    (render(result, seq, opts), centers)
+}
+
+/// Render a DrawResult as a `rows` × `cols` grid of SVGs, row-major from the
+/// top-left, for printing large structures across pages.
+///
+/// Every tile carries the full drawing; only its viewBox differs, so content
+/// outside the tile is clipped. The legend is drawn only on the first tile, to the
+/// right of it. Zero rows or columns yield no tiles.
+pub fn render_tiles(
+    result: &DrawResult,
+    seq: Option<&str>,
+    opts: &SvgOptions,
+    rows: usize,
+    cols: usize,
+) -> Vec<String> {
+    if rows == 0 || cols == 0 {
+        return vec![];
+    }
+    let opts = resolve_options(opts, result.layout.bases.len());
+    let (body, frame) = render_body(result, seq, &opts);
+    let tile_w = frame.struct_w / cols as f64;
+    let tile_h = frame.vb_h / rows as f64;
+
+    let mut tiles = Vec::with_capacity(rows * cols);
+    for row in 0..rows {
+        for col in 0..cols {
+            let x = frame.vb_x + col as f64 * tile_w;
+            let y = frame.vb_y + row as f64 * tile_h;
+            let first = row == 0 && col == 0;
+            let legend_w = if first { frame.legend_w } else { 0.0 };
+
+            let mut svg = String::with_capacity(body.len() + 256);
+            write_svg_open(&mut svg, x, y, tile_w + legend_w, tile_h, &opts);
+            if opts.embed_source {
+                write_metadata(&mut svg, result, seq, None);
+            }
+            svg.push_str(&body);
+            if first && opts.legend != Legend::None {
+                render_legend(&mut svg, x + tile_w, y, tile_h, &opts);
+            }
+            svg.push_str("</svg>");
+            tiles.push(svg);
+        }
+    }
+    tiles
+}
+
+/// Render several structures side by side in one SVG, as a grid `cols` wide filled
+/// row-major, for comparing conformations. Each item is a result and its sequence.
+///
+/// Every cell is sized to the largest structure and the structures are centered in
+/// them, each under a small label with its 1-based position in `items`. A legend, if
+/// any, is drawn once to the right of the grid. No items or zero columns yield an
+/// empty string.
+pub fn render_panel(
+    items: &[(DrawResult, Option<String>)],
+    opts: &SvgOptions,
+    cols: usize,
+) -> String {
+    if items.is_empty() || cols == 0 {
+        return String::new();
+    }
+    let cells: Vec<(String, Frame, Cow<'_, SvgOptions>)> = items
+        .iter()
+        .map(|(result, seq)| {
+            let opts = resolve_options(opts, result.layout.bases.len());
+            let (body, frame) = render_body(result, seq.as_deref(), &opts);
+            (body, frame, opts)
+        })
+        .collect();
+    let cell_w = cells.iter().map(|c| c.1.struct_w).fold(0.0, f64::max);
+    let cell_h = cells.iter().map(|c| c.1.vb_h).fold(0.0, f64::max);
+    let label_h = opts.font_size + 8.0;
+    let row_h = label_h + cell_h;
+    let rows = items.len().div_ceil(cols);
+    let cols = cols.min(items.len());
+    let (first_opts, legend_w) = (&cells[0].2, cells[0].1.legend_w);
+    let (width, height) = (cols as f64 * cell_w, rows as f64 * row_h);
+    let prec = opts.coord_precision;
+
+    let mut svg = String::with_capacity(cells.iter().map(|c| c.0.len() + 128).sum());
+    write_svg_open(&mut svg, 0.0, 0.0, width + legend_w, height, first_opts);
+    for (k, (body, frame, _)) in cells.iter().enumerate() {
+        let (x, y) = ((k % cols) as f64 * cell_w, (k / cols) as f64 * row_h);
+        let _ = write!(
+            svg,
+            r#"<text x="{:.prec$}" y="{:.prec$}" font-size="{}" text-anchor="middle" dominant-baseline="central" fill="{}">{}</text>"#,
+            x + cell_w / 2.0,
+            y + label_h / 2.0,
+            opts.font_size,
+            first_opts.label_color,
+            k + 1
+        );
+        // Center the structure's frame in the cell below the label
+        let dx = x + (cell_w - frame.struct_w) / 2.0 - frame.vb_x;
+        let dy = y + label_h + (cell_h - frame.vb_h) / 2.0 - frame.vb_y;
+        let _ = write!(svg, r#"<g transform="translate({dx:.prec$},{dy:.prec$})">"#);
+        svg.push_str(body);
+        svg.push_str("</g>");
+    }
+    if first_opts.legend != Legend::None {
+        render_legend(&mut svg, width, 0.0, height, first_opts);
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Render a DrawResult as a bare skeleton for import into vector editors: one
+/// straight `<polyline>` through the base centers of each strand and one `<line>`
+/// per pair bond.
+///
+/// Only scale, padding, stroke widths, backbone and pair colors, and coordinate
+/// options apply; there are no arcs, markers, labels, arrows, or legend.
+pub fn render_skeleton(result: &DrawResult, opts: &SvgOptions) -> String {
+    let opts = if opts.sequence_track {
+        Cow::Owned(SvgOptions {
+            sequence_track: false,
+            ..opts.clone()
+        })
     } else {
-        opts
+        Cow::Borrowed(opts)
+    };
+    let frame = compute_frame(result, &opts);
+    let (scale, prec) = (opts.scale, opts.coord_precision);
+    let bases = &result.layout.bases;
+    let point = |i: usize| snap(bases[i].x * scale, -bases[i].y * scale, &opts);
+
+    let mut svg = String::with_capacity(64 * bases.len() + 256);
+    write_svg_open(
+        &mut svg,
+        frame.vb_x,
+        frame.vb_y,
+        frame.struct_w,
+        frame.vb_h,
+        &opts,
+    );
+    for (i, &j) in result.pairs.iter().enumerate().filter(|&(i, &j)| i < j) {
+        let ((x1, y1), (x2, y2)) = (point(i), point(j));
+        let _ = write!(
+            svg,
+            r#"<line x1="{:.prec$}" y1="{:.prec$}" x2="{:.prec$}" y2="{:.prec$}" stroke-linecap="round" stroke-width="{}" stroke="{}" />"#,
+            x1, y1, x2, y2, opts.pair_width, opts.pair_color
+        );
+    }
+    for (start, end) in result.strands() {
+        svg.push_str(r#"<polyline points=""#);
+        for i in start..end {
+            let (x, y) = point(i);
+            let sep = if i == start { "" } else { " " };
+            let _ = write!(svg, "{sep}{x:.prec$},{y:.prec$}");
+        }
+        let _ = write!(
+            svg,
+            r#"" fill="none" stroke-linejoin="round" stroke-linecap="round" stroke-width="{}" stroke="{}" />"#,
+            opts.backbone_width, opts.backbone_color
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Structure extent in SVG units, shared by a full render and its tiles.
+struct Frame {
+    vb_x: f64,
+    vb_y: f64,
+    /// Width of the structure area, excluding the legend
+    struct_w: f64,
+    vb_h: f64,
+    /// Width reserved for the legend on the right
+    legend_w: f64,
+}
+
+/// Expand a nucleotide palette, apply monochrome, then turn probabilities or position gradient into per-base colors.
+fn resolve_options(opts: &SvgOptions, n: usize) -> Cow<'_, SvgOptions> {
+    let mut opts = Cow::Borrowed(opts);
+    if let (None, Some(palette)) = (&opts.base_colors, opts.nucleotide_palette) {
+        opts = Cow::Owned(SvgOptions {
+            base_colors: Some(palette.colors().map(String::from)),
+            ..opts.into_owned()
+        });
+    }
+    if opts.auto_stroke {
+        opts = Cow::Owned(opts.resolve_auto_stroke());
+    }
+    if opts.monochrome {
+        opts = Cow::Owned(opts.resolve_monochrome());
+    }
+    if let Some(focus) = opts.focus {
+        opts = Cow::Owned(opts.resolve_focus(focus, n));
+    }
+    if opts.probabilities.is_some() {
+        opts = Cow::Owned(opts.resolve_probabilities());
+    } else if opts.position_gradient {
+        opts = Cow::Owned(opts.resolve_position_gradient(n));
+    }
+    opts
+}
+
+fn write_svg_open(svg: &mut dyn Write, x: f64, y: f64, w: f64, h: f64, opts: &SvgOptions) {
+    let prec = opts.coord_precision;
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.prec$} {:.prec$} {:.prec$} {:.prec$}""#,
+        x, y, w, h
+    );
+    if let Some(par) = &opts.preserve_aspect_ratio {
+        let _ = write!(svg, r#" preserveAspectRatio="{}""#, xml_escape(par));
+    }
+    if let Some(label) = &opts.aria_label {
+        let label = match label.as_str() {
+            "" => "RNA secondary structure diagram",
+            s => s,
+        };
+        let _ = write!(svg, r#" role="img" aria-label="{}""#, xml_escape(label));
+    }
+    let _ = svg.write_char('>');
+}
+
+/// `<metadata>` recording the structure (`source`, or rebuilt from the pair table)
+/// and sequence as attributes of an `rnadraw:source` element.
+fn write_metadata(
+    svg: &mut dyn Write,
+    result: &DrawResult,
+    seq: Option<&str>,
+    source: Option<&str>,
+) {
+    let structure = match source {
+        Some(s) => s.to_string(),
+        None => dot_bracket(result),
+    };
+    let _ = write!(
+        svg,
+        r#"<metadata><rnadraw:source xmlns:rnadraw="https://github.com/mulatta/rnadraw" structure="{}""#,
+        xml_escape(&structure)
+    );
+    if let Some(seq) = seq {
+        let _ = write!(svg, r#" sequence="{}""#, xml_escape(seq));
+    }
+    let _ = svg.write_str("/></metadata>");
+}
+
+/// Dot-bracket-plus notation of a result's pairs and strand breaks.
+fn dot_bracket(result: &DrawResult) -> String {
+    let mut s = String::with_capacity(result.pairs.len() + result.nicks.len());
+    for (i, &j) in result.pairs.iter().enumerate() {
+        if i > 0 && result.nicks.contains(&i) {
+            s.push('+');
+        }
+        s.push(match j.cmp(&i) {
+            std::cmp::Ordering::Greater => '(',
+            std::cmp::Ordering::Less => ')',
+            std::cmp::Ordering::Equal => '.',
+        });
+    }
+    s
+}
+
+/// Escape text for use in XML character data or a double-quoted attribute.
+fn xml_escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Defs and drawing layers (everything inside `<svg>` except the legend).
+fn render_body(result: &DrawResult, seq: Option<&str>, opts: &SvgOptions) -> (String, Frame) {
+    let frame = compute_frame(result, opts);
+    let mut svg = String::with_capacity(4096);
+    write_body(&mut svg, result, seq, &frame, opts);
+    (svg, frame)
+}
+
+/// ViewBox of the structure area (padding, `square`, and sequence track
+/// included) plus the legend width.
+fn compute_frame(result: &DrawResult, opts: &SvgOptions) -> Frame {
+    let (min_x, min_y, max_x, max_y) =
+        compute_bbox(&result.layout.bases, &result.layout.loops, opts.scale, opts);
+    let [top, right, bottom, left] = opts.padding_sides.unwrap_or([opts.padding; 4]);
+    let mut frame = Frame {
+        vb_x: min_x - left,
+        vb_y: min_y - top,
+        struct_w: (max_x - min_x) + left + right,
+        vb_h: (max_y - min_y) + top + bottom,
+        // Reserve space for legend on the right
+        legend_w: legend_width(&opts.legend),
     };
 
-    // Strip strand break markers (+) from sequence so indices align with bases
+    if opts.square {
+        let side = frame.struct_w.max(frame.vb_h);
+        frame.vb_x -= (side - frame.struct_w) / 2.0;
+        frame.vb_y -= (side - frame.vb_h) / 2.0;
+        frame.struct_w = side;
+        frame.vb_h = side;
+    }
+    if opts.sequence_track {
+        frame.vb_h += TRACK_HEIGHT;
+    }
+    frame
+}
+
+fn write_body(
+    svg: &mut dyn Write,
+    result: &DrawResult,
+    seq: Option<&str>,
+    frame: &Frame,
+    opts: &SvgOptions,
+) {
+    // Strip strand break markers (+ or &) from sequence so indices align with bases
     let clean_seq;
     let seq = match seq {
-        Some(s) if s.contains('+') => {
-            clean_seq = s.replace('+', "");
+        Some(s) if s.contains(['+', '&']) => {
+            clean_seq = s.replace(['+', '&'], "");
             Some(clean_seq.as_str())
         }
         other => other,
     };
 
     let bases = &result.layout.bases;
-    let loops = &result.layout.loops;
     let segments = &result.segments;
     let pairs = &result.pairs;
-    let nicks = &result.nicks;
+    let strands = result.strands();
     let scale = opts.scale;
 
-    let (min_x, min_y, max_x, max_y) = compute_bbox(bases, loops, scale, opts);
-    let pad = opts.padding;
-    let vb_x = min_x - pad;
-    let vb_y = min_y - pad;
-    let struct_w = (max_x - min_x) + 2.0 * pad;
-    let vb_h = (max_y - min_y) + 2.0 * pad;
-
-    // Reserve space for legend on the right
-    let legend_w = match opts.legend {
-        Legend::None => 0.0,
-        Legend::Nucleotide => 80.0,
-        Legend::Probability => 100.0,
-    };
-    let vb_w = struct_w + legend_w;
-
-    let mut svg = String::with_capacity(4096);
-    let _ = write!(
-        svg,
-        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.2} {:.2} {:.2} {:.2}">"#,
-        vb_x, vb_y, vb_w, vb_h
-    );
-
     // Arrow marker definition (must be before first use)
-    if opts.show_arrows {
+    if opts.show_arrows && !opts.arrows_as_paths {
         let _ = write!(
             svg,
             r#"<defs><marker markerWidth="3" markerHeight="3" refX="10" refY="10" viewBox="0 0 20 20" orient="auto" id="arrowblack" markerUnits="strokeWidth"><path d="M0 0 10 0 20 10 10 20 0 20 10 10Z" fill="{}"/></marker></defs>"#,
@@ -206,32 +1093,110 @@ pub fn render(result: &DrawResult, seq: Option<&str>, opts: &SvgOptions) -> Stri
         );
     }
 
+    write_pattern_defs(svg, opts);
+
     // Layer order (back → front):
+    // 0. Debug grid (optional)
+    if let Some(spacing) = opts.debug_grid {
+        layer(svg, "grid", opts, |svg| {
+            render_debug_grid(svg, frame, spacing * scale, opts.coord_precision)
+        });
+    }
     // 1. Pair bonds (back)
-    render_pair_bonds(&mut svg, bases, pairs, scale, opts);
+    layer(svg, "bonds", opts, |svg| {
+        render_pair_bonds(svg, bases, pairs, &strands, scale, opts)
+    });
     // 2. Backbone
-    render_backbone(&mut svg, bases, segments, nicks, scale, opts);
+    layer(svg, "backbone", opts, |svg| {
+        render_backbone(svg, bases, segments, &strands, scale, opts)
+    });
     // 3. 3' arrows (on backbone, before circles)
     if opts.show_arrows {
-        render_end_arrows(&mut svg, bases, segments, nicks, scale, opts);
+        layer(svg, "arrows", opts, |svg| {
+            render_end_arrows(svg, bases, segments, &strands, scale, opts)
+        });
     }
     // 4. Base markers (circles — on top, covering backbone/bond endpoints)
-    render_base_markers(&mut svg, bases, seq, scale, opts);
+    if opts.show_bases {
+        layer(svg, "bases", opts, |svg| {
+            render_base_markers(svg, bases, seq, scale, opts)
+        });
+    }
     // 5. Labels (front, optional)
     if opts.show_labels {
         if let Some(sequence) = seq {
-            render_labels(&mut svg, bases, sequence, scale, opts);
+            layer(svg, "labels", opts, |svg| {
+                render_labels(svg, result, sequence, scale, opts)
+            });
         }
     }
+    // 6. Linear sequence track (below the structure, optional)
+    if opts.sequence_track {
+        let [_, right, _, left] = opts.padding_sides.unwrap_or([opts.padding; 4]);
+        let track_y = frame.vb_y + frame.vb_h - TRACK_HEIGHT;
+        layer(svg, "sequence-track", opts, |svg| {
+            render_sequence_track(
+                svg,
+                bases.len(),
+                seq,
+                frame.vb_x + left,
+                track_y,
+                frame.struct_w - left - right,
+                opts,
+            )
+        });
+    }
+}
+
+/// Draw one layer, wrapped in `<g id="{id}">` when `group_layers` is set.
+fn layer(svg: &mut dyn Write, id: &str, opts: &SvgOptions, draw: impl FnOnce(&mut dyn Write)) {
+    if opts.group_layers {
+        let _ = write!(svg, r#"<g id="{id}">"#);
+    }
+    draw(&mut *svg);
+    if opts.group_layers {
+        let _ = svg.write_str("</g>");
+    }
+}
 
-    // 6. Legend (rightmost)
-    if opts.legend != Legend::None {
-        let legend_x = vb_x + struct_w;
-        render_legend(&mut svg, legend_x, vb_y, vb_h, opts);
+/// Hatch patterns that stand in for nucleotide colors in monochrome mode.
+fn write_pattern_defs(svg: &mut dyn Write, opts: &SvgOptions) {
+    if !(opts.monochrome && opts.base_colors.is_some()) {
+        return;
+    }
+    let _ = svg.write_str("<defs>");
+    for (id, body) in MONO_PATTERNS {
+        let _ = write!(
+            svg,
+            r#"<pattern id="{id}" patternUnits="userSpaceOnUse" width="4" height="4"><rect width="4" height="4" fill="white"/>{body}</pattern>"#,
+        );
     }
+    let _ = svg.write_str("</defs>");
+}
 
-    svg.push_str("</svg>");
-    svg
+/// Gridlines every `step` pixels across the structure area, aligned to the layout
+/// origin, plus the two axes through the origin.
+fn render_debug_grid(svg: &mut dyn Write, frame: &Frame, step: f64, prec: usize) {
+    if !(step.is_finite() && step > 0.0) {
+        return;
+    }
+    let (x0, x1) = (frame.vb_x, frame.vb_x + frame.struct_w);
+    let (y0, y1) = (frame.vb_y, frame.vb_y + frame.vb_h);
+    let _ = svg.write_str(r#"<path d=""#);
+    let mut k = (x0 / step).ceil();
+    while k * step <= x1 {
+        let _ = write!(svg, "M{:.prec$} {y0:.prec$}V{y1:.prec$}", k * step);
+        k += 1.0;
+    }
+    let mut k = (y0 / step).ceil();
+    while k * step <= y1 {
+        let _ = write!(svg, "M{x0:.prec$} {:.prec$}H{x1:.prec$}", k * step);
+        k += 1.0;
+    }
+    let _ = write!(
+        svg,
+        r##"" fill="none" stroke="#dddddd" stroke-width="0.5" /><path d="M{x0:.prec$} 0H{x1:.prec$}M0 {y0:.prec$}V{y1:.prec$}" fill="none" stroke="#999999" stroke-width="1" />"##
+    );
 }
 
 fn compute_bbox(
@@ -245,10 +1210,23 @@ fn compute_bbox(
     let mut max_x = f64::NEG_INFINITY;
     let mut max_y = f64::NEG_INFINITY;
 
-    // Account for base circle visual extent (radius + half stroke)
-    let base_extent = opts.base_radius + opts.base_stroke_width * 0.5;
+    // Account for base circle visual extent (radius + half stroke), or just the
+    // backbone stroke when circles are hidden
+    let base_extent = if opts.show_bases {
+        opts.base_radius + opts.base_stroke_width * 0.5
+    } else {
+        opts.backbone_width * 0.5
+    };
+
+    // A focus or window frames only its own bases; fall back to everything if it is empty
+    let range = opts.focus.or(opts.window);
+    let in_range = |i: usize| range.is_none_or(|(a, b)| a <= i && i <= b);
+    let windowed = range.is_some() && (0..bases.len()).any(in_range);
 
-    for b in bases {
+    for (i, b) in bases.iter().enumerate() {
+        if windowed && !in_range(i) {
+            continue;
+        }
         let sx = b.x * scale;
         let sy = -b.y * scale;
         min_x = min_x.min(sx - base_extent);
@@ -257,10 +1235,10 @@ fn compute_bbox(
         max_y = max_y.max(sy + base_extent);
     }
 
-    for l in loops {
+    for l in loops.iter().filter(|_| !windowed) {
         let cx = l.x * scale;
         let cy = -l.y * scale;
-        let r = l.radius * scale;
+        let r = l.ellipse.map_or(l.radius, |e| e.ry.max(l.radius)) * scale;
         min_x = min_x.min(cx - r);
         min_y = min_y.min(cy - r);
         max_x = max_x.max(cx + r);
@@ -271,63 +1249,270 @@ fn compute_bbox(
 }
 
 fn render_pair_bonds(
-    svg: &mut String,
+    svg: &mut dyn Write,
     bases: &[Base],
     pairs: &[usize],
+    strands: &[(usize, usize)],
     scale: f64,
     opts: &SvgOptions,
 ) {
+    let prec = opts.coord_precision;
     for (i, &j) in pairs.iter().enumerate() {
         if i >= j {
             continue;
         }
+        let (in_i, in_j) = (opts.in_window(i), opts.in_window(j));
+        if !in_i && !in_j {
+            continue;
+        }
         let bi = &bases[i];
         let bj = &bases[j];
-        let x1 = bi.x * scale;
-        let y1 = -bi.y * scale;
-        let x2 = bj.x * scale;
-        let y2 = -bj.y * scale;
-        let _ = write!(
-            svg,
-            r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke-linecap="round" stroke-width="{}" stroke="{}" />"#,
-            x1, y1, x2, y2, opts.pair_width, opts.pair_color
-        );
+        let (x1, y1) = snap(bi.x * scale, -bi.y * scale, opts);
+        let (x2, y2) = snap(bj.x * scale, -bj.y * scale, opts);
+        let mut data = if opts.data_attrs {
+            format!(r#" data-i="{i}" data-j="{j}""#)
+        } else {
+            String::new()
+        };
+        let strand_of = |k: usize| strands.iter().position(|&(s, e)| s <= k && k < e);
+        let color = match &opts.interstrand_pair_color {
+            Some(c) if strand_of(i) != strand_of(j) => c,
+            _ => &opts.pair_color,
+        };
+        let emphasized = opts.emphasized_pairs.as_ref().is_none_or(|list| {
+            list.iter()
+                .any(|&(a, b)| (a, b) == (i, j) || (b, a) == (i, j))
+        });
+        // Bond leaving the window, or not among the emphasized ones
+        if !(in_i && in_j && emphasized) {
+            data.push_str(r#" opacity="0.3""#);
+        }
+        match opts.pair_style {
+            PairStyle::Line | PairStyle::LineWithDot => {
+                let _ = write!(
+                    svg,
+                    r#"<line x1="{:.prec$}" y1="{:.prec$}" x2="{:.prec$}" y2="{:.prec$}" stroke-linecap="round" stroke-width="{}" stroke="{}"{} />"#,
+                    x1, y1, x2, y2, opts.pair_width, color, data
+                );
+            }
+            PairStyle::Tick => {
+                let (dx, dy) = (bj.x - bi.x, bj.y - bi.y);
+                let len = dx.hypot(dy) * scale;
+                let t = if len > 0.0 {
+                    (opts.pair_tick_len / len).min(0.5)
+                } else {
+                    0.0
+                };
+                for (b, sign) in [(bi, 1.0), (bj, -1.0)] {
+                    let (sx, sy) = snap(b.x * scale, -b.y * scale, opts);
+                    let (ex, ey) = snap(
+                        (b.x + sign * t * dx) * scale,
+                        -(b.y + sign * t * dy) * scale,
+                        opts,
+                    );
+                    let _ = write!(
+                        svg,
+                        r#"<line x1="{:.prec$}" y1="{:.prec$}" x2="{:.prec$}" y2="{:.prec$}" stroke-linecap="round" stroke-width="{}" stroke="{}"{} />"#,
+                        sx, sy, ex, ey, opts.pair_width, color, data
+                    );
+                }
+            }
+            PairStyle::MidpointDot => {}
+        }
+        if matches!(
+            opts.pair_style,
+            PairStyle::MidpointDot | PairStyle::LineWithDot
+        ) {
+            let (cx, cy) = snap(
+                (bi.x + bj.x) / 2.0 * scale,
+                -(bi.y + bj.y) / 2.0 * scale,
+                opts,
+            );
+            let _ = write!(
+                svg,
+                r#"<circle r="{}" cx="{:.prec$}" cy="{:.prec$}" fill="{}"{} />"#,
+                opts.pair_width * 2.0,
+                cx,
+                cy,
+                color,
+                data
+            );
+        }
     }
 }
 
 fn render_backbone(
-    svg: &mut String,
+    svg: &mut dyn Write,
     bases: &[Base],
     segments: &[Vec<Segment>],
-    nicks: &[usize],
+    strands: &[(usize, usize)],
     scale: f64,
     opts: &SvgOptions,
 ) {
-    let n = bases.len();
-    if n == 0 {
-        return;
-    }
-
-    let mut strand_starts: Vec<usize> = nicks.to_vec();
-    strand_starts.sort_unstable();
-    strand_starts.dedup();
+    for (si, &(start, end)) in strands.iter().enumerate() {
+        let color = opts
+            .strand_colors
+            .as_ref()
+            .and_then(|c| c.get(si))
+            .unwrap_or(&opts.backbone_color);
 
-    for si in 0..strand_starts.len() {
-        let start = strand_starts[si];
-        let end = if si + 1 < strand_starts.len() {
-            strand_starts[si + 1]
-        } else {
-            n
-        };
-        if start >= end {
+        if opts.merge_backbone {
+            render_strand_path(svg, bases, segments, (start, end), scale, color, opts);
             continue;
         }
 
         // Render each half-segment as individual <line> or <path>.
         // Round stroke-linecap on each piece creates smooth overlapping joins.
         for i in start..(end - 1) {
-            render_individual_segment(svg, &segments[i][1], scale, opts);
-            render_individual_segment(svg, &segments[i + 1][0], scale, opts);
+            if !(opts.in_window(i) && opts.in_window(i + 1)) {
+                continue;
+            }
+            render_individual_segment(svg, &segments[i][1], scale, color, opts);
+            render_individual_segment(svg, &segments[i + 1][0], scale, color, opts);
+        }
+    }
+}
+
+/// Render one strand's backbone as a single `<path>` (see `merge_backbone`),
+/// starting a new subpath after each link hidden by `window`.
+fn render_strand_path(
+    svg: &mut dyn Write,
+    bases: &[Base],
+    segments: &[Vec<Segment>],
+    (start, end): (usize, usize),
+    scale: f64,
+    color: &str,
+    opts: &SvgOptions,
+) {
+    let prec = opts.coord_precision;
+    let mut d = String::new();
+    let mut open = false;
+    for i in start..(end - 1) {
+        if !(opts.in_window(i) && opts.in_window(i + 1)) {
+            open = false;
+            continue;
+        }
+        let b = &bases[i];
+        let mut at = snap(b.x * scale, -b.y * scale, opts);
+        // Outgoing half of base i, then incoming half of base i + 1, each traced
+        // from the end nearer the current point
+        for seg in [&segments[i][1], &segments[i + 1][0]] {
+            let Some(piece) = PathPiece::new(seg, scale, opts) else {
+                continue;
+            };
+            let dist = |p: (f64, f64)| (p.0 - at.0).hypot(p.1 - at.1);
+            let piece = if dist(piece.from) <= dist(piece.to) {
+                piece
+            } else {
+                piece.reversed()
+            };
+            if !open {
+                let _ = write!(d, "M{:.prec$} {:.prec$}", piece.from.0, piece.from.1);
+                open = true;
+            }
+            let (x, y) = piece.to;
+            match &piece.arc {
+                None => {
+                    let _ = write!(d, " L{x:.prec$} {y:.prec$}");
+                }
+                Some(arc) => {
+                    let _ = write!(
+                        d,
+                        " A{:.prec$} {:.prec$} {} {} {} {x:.prec$} {y:.prec$}",
+                        arc.rx,
+                        arc.ry,
+                        arc.rotation,
+                        u8::from(arc.large_arc),
+                        u8::from(arc.sweep)
+                    );
+                }
+            }
+            at = piece.to;
+        }
+    }
+    if d.is_empty() {
+        return;
+    }
+    let _ = write!(
+        svg,
+        r#"<path d="{d}" fill="none" stroke-linejoin="round" stroke-linecap="round" stroke-width="{}" stroke="{}" />"#,
+        opts.backbone_width, color
+    );
+}
+
+/// A backbone half-segment in pixel space, as one command of a merged path.
+struct PathPiece {
+    from: (f64, f64),
+    to: (f64, f64),
+    /// Set when drawn as an arc rather than a straight line
+    arc: Option<ArcCommand>,
+}
+
+/// Parameters of an SVG elliptical-arc command.
+struct ArcCommand {
+    rx: f64,
+    ry: f64,
+    rotation: String,
+    large_arc: bool,
+    sweep: bool,
+}
+
+impl PathPiece {
+    /// None for degenerate pieces, which draw nothing.
+    fn new(seg: &Segment, scale: f64, opts: &SvgOptions) -> Option<Self> {
+        let prec = opts.coord_precision;
+        let line = |from: (f64, f64), to: (f64, f64)| {
+            ((to.0 - from.0).hypot(to.1 - from.1) >= 0.1).then_some(Self {
+                from,
+                to,
+                arc: None,
+            })
+        };
+        match seg {
+            Segment::Line(l) => line(
+                snap(l.x * scale, -l.y * scale, opts),
+                snap(l.x1 * scale, -l.y1 * scale, opts),
+            ),
+            Segment::Arc(arc) => {
+                if (arc.t1 - arc.t2).abs() < 1e-12 {
+                    return None;
+                }
+                let (p, q, large_arc, sweep) = arc_svg_params(arc);
+                let from = snap(p.0 * scale, p.1 * scale, opts);
+                let to = snap(q.0 * scale, q.1 * scale, opts);
+                if normalize_angle(arc.t1 - arc.t2).abs() < opts.min_arc_sweep {
+                    return line(from, to);
+                }
+                let rx = arc.r * scale;
+                let (ry, rotation) = match &arc.ellipse {
+                    None => (rx, "0".to_string()),
+                    // SVG's y axis points down, so the rotation flips sign
+                    Some(e) => (e.ry * scale, format!("{:.prec$}", -e.rotation.to_degrees())),
+                };
+                Some(Self {
+                    from,
+                    to,
+                    arc: Some(ArcCommand {
+                        rx,
+                        ry,
+                        rotation,
+                        large_arc,
+                        sweep,
+                    }),
+                })
+            }
+        }
+    }
+
+    /// The same piece traced the other way.
+    fn reversed(self) -> Self {
+        Self {
+            from: self.to,
+            to: self.from,
+            arc: self.arc.map(|arc| ArcCommand {
+                sweep: !arc.sweep,
+                ..arc
+            }),
         }
     }
 }
@@ -336,48 +1521,98 @@ fn render_backbone(
 ///
 /// LINE → `<line>`, ARC → `<path d="M...A...">`.
 /// Each has `stroke-linecap="round"` so overlapping endpoints merge smoothly.
-fn render_individual_segment(svg: &mut String, seg: &Segment, scale: f64, opts: &SvgOptions) {
+fn render_individual_segment(
+    svg: &mut dyn Write,
+    seg: &Segment,
+    scale: f64,
+    color: &str,
+    opts: &SvgOptions,
+) {
+    let prec = opts.coord_precision;
     match seg {
         Segment::Line(line) => {
-            let x1 = line.x * scale;
-            let y1 = -line.y * scale;
-            let x2 = line.x1 * scale;
-            let y2 = -line.y1 * scale;
-            let dx = x2 - x1;
-            let dy = y2 - y1;
-            if dx * dx + dy * dy < 0.01 {
-                return;
-            }
-            let _ = write!(
-                svg,
-                r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke-linecap="round" stroke-opacity="1" stroke-width="{}" stroke="{}" />"#,
-                x1, y1, x2, y2, opts.backbone_width, opts.backbone_color
-            );
+            let start = snap(line.x * scale, -line.y * scale, opts);
+            let end = snap(line.x1 * scale, -line.y1 * scale, opts);
+            write_backbone_line(svg, start, end, color, opts);
         }
         Segment::Arc(arc) => {
             if (arc.t1 - arc.t2).abs() < 1e-12 {
                 return;
             }
-            let r = arc.r * scale;
-            let sx = (arc.x + arc.r * arc.t2.cos()) * scale;
-            let sy = -(arc.y + arc.r * arc.t2.sin()) * scale;
-            let ex = (arc.x + arc.r * arc.t1.cos()) * scale;
-            let ey = -(arc.y + arc.r * arc.t1.sin()) * scale;
+            let ((x2, y2), (x1, y1), large_arc, sweep) = arc_svg_params(arc);
+            let (sx, sy) = snap(x2 * scale, y2 * scale, opts);
+            let (ex, ey) = snap(x1 * scale, y1 * scale, opts);
 
-            let delta = normalize_angle(arc.t1 - arc.t2);
-            let large_arc = if delta.abs() > PI { 1 } else { 0 };
-            let sweep = if delta > 0.0 { 0 } else { 1 };
+            if normalize_angle(arc.t1 - arc.t2).abs() < opts.min_arc_sweep {
+                write_backbone_line(svg, (sx, sy), (ex, ey), color, opts);
+                return;
+            }
+            let (large_arc, sweep) = (u8::from(large_arc), u8::from(sweep));
+
+            let r = arc.r * scale;
+            let (ry, rotation) = match &arc.ellipse {
+                None => (r, Cow::Borrowed("0")),
+                // SVG's y axis points down, so the rotation flips sign
+                Some(e) => (
+                    e.ry * scale,
+                    Cow::Owned(format!("{:.prec$}", -e.rotation.to_degrees())),
+                ),
+            };
 
             let _ = write!(
                 svg,
-                r#"<path d="M{:.2} {:.2} A{:.2} {:.2} 0 {} {} {:.2} {:.2}" fill="none" stroke-linejoin="round" stroke-linecap="round" stroke-width="{}" stroke="{}" />"#,
-                sx, sy, r, r, large_arc, sweep, ex, ey, opts.backbone_width, opts.backbone_color
+                r#"<path d="M{:.prec$} {:.prec$} A{:.prec$} {:.prec$} {} {} {} {:.prec$} {:.prec$}" fill="none" stroke-linejoin="round" stroke-linecap="round" stroke-width="{}" stroke="{}" />"#,
+                sx, sy, r, ry, rotation, large_arc, sweep, ex, ey, opts.backbone_width, color
             );
         }
     }
 }
 
-fn normalize_angle(mut a: f64) -> f64 {
+/// Endpoints and flags of the SVG elliptical-arc command drawing `arc` from `t2`
+/// to `t1`: `(start, end, large_arc, sweep)`.
+///
+/// Points are in SVG orientation (y negated) but unscaled, so multiply them and the
+/// radius by the drawing scale. Then `M start A r r 0 large_arc sweep end` traces
+/// the arc the short way round, as `render` does.
+pub fn arc_svg_params(arc: &ArcSegment) -> ((f64, f64), (f64, f64), bool, bool) {
+    let (x2, y2) = arc.point(arc.t2);
+    let (x1, y1) = arc.point(arc.t1);
+    let delta = normalize_angle(arc.t1 - arc.t2);
+    ((x2, -y2), (x1, -y1), delta.abs() > PI, delta <= 0.0)
+}
+
+/// Straight backbone `<line>` between two pixel-space points, skipped when shorter
+/// than 0.1 px.
+fn write_backbone_line(
+    svg: &mut dyn Write,
+    (x1, y1): (f64, f64),
+    (x2, y2): (f64, f64),
+    color: &str,
+    opts: &SvgOptions,
+) {
+    let prec = opts.coord_precision;
+    let dx = x2 - x1;
+    let dy = y2 - y1;
+    if dx * dx + dy * dy < 0.01 {
+        return;
+    }
+    let _ = write!(
+        svg,
+        r#"<line x1="{:.prec$}" y1="{:.prec$}" x2="{:.prec$}" y2="{:.prec$}" stroke-linecap="round" stroke-opacity="1" stroke-width="{}" stroke="{}" />"#,
+        x1, y1, x2, y2, opts.backbone_width, color
+    );
+}
+
+/// Round a pixel-space point to the nearest 0.5 px when `snap_pixels` is set.
+fn snap(x: f64, y: f64, opts: &SvgOptions) -> (f64, f64) {
+    if opts.snap_pixels {
+        ((x * 2.0).round() / 2.0, (y * 2.0).round() / 2.0)
+    } else {
+        (x, y)
+    }
+}
+
+pub(crate) fn normalize_angle(mut a: f64) -> f64 {
     while a > PI {
         a -= 2.0 * PI;
     }
@@ -389,38 +1624,24 @@ fn normalize_angle(mut a: f64) -> f64 {
 
 /// Render 3' arrows at the end of each strand.
 fn render_end_arrows(
-    svg: &mut String,
+    svg: &mut dyn Write,
     bases: &[Base],
     segments: &[Vec<Segment>],
-    nicks: &[usize],
+    strands: &[(usize, usize)],
     scale: f64,
     opts: &SvgOptions,
 ) {
-    let n = bases.len();
-    if n == 0 {
-        return;
-    }
-
-    let mut strand_starts: Vec<usize> = nicks.to_vec();
-    strand_starts.sort_unstable();
-    strand_starts.dedup();
-
-    for si in 0..strand_starts.len() {
-        let start = strand_starts[si];
-        let end_idx = if si + 1 < strand_starts.len() {
-            strand_starts[si + 1] - 1
-        } else {
-            n - 1
-        };
+    let prec = opts.coord_precision;
+    for (si, &(start, end)) in strands.iter().enumerate() {
+        let end_idx = end - 1;
 
         // Need at least 2 bases for arrow direction
-        if end_idx <= start {
+        if end_idx <= start || !opts.in_window(end_idx) {
             continue;
         }
 
         let base = &bases[end_idx];
-        let bx = base.x * scale;
-        let by = -base.y * scale;
+        let (bx, by) = snap(base.x * scale, -base.y * scale, opts);
 
         // Use INCOMING segment to determine 3' arrow direction
         // (outgoing points back into the structure via external loop)
@@ -428,6 +1649,7 @@ fn render_end_arrows(
         else {
             continue;
         };
+        let (ax, ay) = snap(ax, ay, opts);
 
         // Skip if arrow would be zero-length
         let dx = ax - bx;
@@ -436,12 +1658,60 @@ fn render_end_arrows(
             continue;
         }
 
+        if !opts.arrows_as_paths {
+            let _ = write!(
+                svg,
+                r#"<line x1="{:.prec$}" y1="{:.prec$}" x2="{:.prec$}" y2="{:.prec$}" stroke-linecap="round" stroke-width="{}" stroke="{}" marker-end="url(#arrowblack)" />"#,
+                bx, by, ax, ay, opts.backbone_width, opts.backbone_color
+            );
+            continue;
+        }
+        let color = opts
+            .strand_colors
+            .as_ref()
+            .and_then(|c| c.get(si))
+            .unwrap_or(&opts.backbone_color);
         let _ = write!(
             svg,
-            r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke-linecap="round" stroke-width="{}" stroke="{}" marker-end="url(#arrowblack)" />"#,
-            bx, by, ax, ay, opts.backbone_width, opts.backbone_color
+            r#"<line x1="{:.prec$}" y1="{:.prec$}" x2="{:.prec$}" y2="{:.prec$}" stroke-linecap="round" stroke-width="{}" stroke="{}" />"#,
+            bx, by, ax, ay, opts.backbone_width, color
         );
+        render_arrowhead(svg, (ax, ay), (dx, dy), color, opts);
+    }
+}
+
+/// Arrowhead outline of the `arrowblack` marker in its 20×20 viewBox, tip along +x.
+const ARROWHEAD: [(f64, f64); 6] = [
+    (0.0, 0.0),
+    (10.0, 0.0),
+    (20.0, 10.0),
+    (10.0, 20.0),
+    (0.0, 20.0),
+    (10.0, 10.0),
+];
+
+/// Draw the `arrowblack` marker shape as a filled path at `tip`, pointing along
+/// `dir`, at the size the marker would have (3 × backbone_width).
+fn render_arrowhead(
+    svg: &mut dyn Write,
+    tip: (f64, f64),
+    dir: (f64, f64),
+    color: &str,
+    opts: &SvgOptions,
+) {
+    let prec = opts.coord_precision;
+    let len = dir.0.hypot(dir.1);
+    let (ux, uy) = (dir.0 / len, dir.1 / len);
+    let s = 3.0 * opts.backbone_width / 20.0;
+    let _ = svg.write_str(r#"<path d="M"#);
+    for (k, &(px, py)) in ARROWHEAD.iter().enumerate() {
+        // Marker reference point (10, 10) sits on the tip; local y is x rotated 90°
+        let (lx, ly) = ((px - 10.0) * s, (py - 10.0) * s);
+        let (x, y) = snap(tip.0 + ux * lx - uy * ly, tip.1 + uy * lx + ux * ly, opts);
+        let sep = if k == 0 { "" } else { " " };
+        let _ = write!(svg, "{sep}{x:.prec$} {y:.prec$}");
     }
+    let _ = write!(svg, r#"Z" fill="{color}" />"#);
 }
 
 /// Compute arrow endpoint by extending the incoming backbone direction past the base.
@@ -470,9 +1740,12 @@ fn arrow_endpoint_from_incoming(
             if (arc.t1 - arc.t2).abs() < 1e-12 {
                 return None;
             }
-            // Radius vector from center to base position (math coords)
-            let rvx = base.x - arc.x;
-            let rvy = base.y - arc.y;
+            // Radius vector from center to base position (math coords); on an
+            // ellipse, the outward normal plays that role
+            let (rvx, rvy) = match &arc.ellipse {
+                None => (base.x - arc.x, base.y - arc.y),
+                Some(e) => e.normal(arc.r, arc.t1),
+            };
             let rv_len = (rvx * rvx + rvy * rvy).sqrt();
             if rv_len < 1e-12 {
                 return None;
@@ -488,8 +1761,10 @@ fn arrow_endpoint_from_incoming(
             };
 
             // Arrow length ≈ half the arc segment length
-            let half_arc_angle = (arc.t1 - arc.t2).abs() * 0.5;
-            let arrow_len = arc.r * half_arc_angle;
+            let arrow_len = match &arc.ellipse {
+                None => arc.r * (arc.t1 - arc.t2).abs() * 0.5,
+                Some(_) => arc.length() * 0.5,
+            };
             let t_len = (tx * tx + ty * ty).sqrt();
             let factor = arrow_len / t_len;
 
@@ -502,31 +1777,101 @@ fn arrow_endpoint_from_incoming(
 }
 
 fn render_base_markers(
-    svg: &mut String,
+    svg: &mut dyn Write,
     bases: &[Base],
     seq: Option<&str>,
     scale: f64,
     opts: &SvgOptions,
 ) {
-    let seq_bytes = seq.map(|s| s.as_bytes());
+    let prec = opts.coord_precision;
+    let seq_chars: Option<Vec<char>> = seq.map(|s| s.chars().collect());
 
     for (i, b) in bases.iter().enumerate() {
-        let cx = b.x * scale;
-        let cy = -b.y * scale;
+        if !opts.in_window(i) {
+            continue;
+        }
+        let (cx, cy) = snap(b.x * scale, -b.y * scale, opts);
 
-        let fill = get_base_fill(i, seq_bytes, opts);
+        let fill = get_base_fill(i, seq_chars.as_deref(), opts);
+        let stroke = opts
+            .per_base_ring_colors
+            .as_ref()
+            .and_then(|c| c.get(i))
+            .map_or_else(|| base_stroke(fill, opts), String::as_str);
 
-        // Fill and stroke same color
+        let shape = opts
+            .per_base_shape
+            .as_ref()
+            .and_then(|s| s.get(i))
+            .unwrap_or(&opts.base_shape);
+        let r = opts.base_radius;
+        let tag = match shape {
+            BaseShape::Circle => {
+                let _ = write!(svg, r#"<circle r="{r}" cx="{cx:.prec$}" cy="{cy:.prec$}""#);
+                "circle"
+            }
+            BaseShape::Square => {
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{:.prec$}" y="{:.prec$}" width="{}" height="{}""#,
+                    cx - r,
+                    cy - r,
+                    2.0 * r,
+                    2.0 * r
+                );
+                "rect"
+            }
+            BaseShape::Diamond => {
+                let _ = write!(
+                    svg,
+                    r#"<polygon points="{:.prec$},{:.prec$} {:.prec$},{:.prec$} {:.prec$},{:.prec$} {:.prec$},{:.prec$}""#,
+                    cx,
+                    cy - r,
+                    cx + r,
+                    cy,
+                    cx,
+                    cy + r,
+                    cx - r,
+                    cy
+                );
+                "polygon"
+            }
+        };
+        // Fill and stroke same color (black outline in monochrome mode)
         let _ = write!(
             svg,
-            r#"<circle r="{}" cx="{:.2}" cy="{:.2}" fill="{}" stroke-width="{}" stroke="{}" />"#,
-            opts.base_radius, cx, cy, fill, opts.base_stroke_width, fill
+            r#" fill="{}" stroke-width="{}" stroke="{}""#,
+            fill, opts.base_stroke_width, stroke
         );
+        let opacity = opts.per_base_opacity.as_ref().and_then(|o| o.get(i));
+        if let Some(&a) = opacity.filter(|a| !a.is_nan()) {
+            let a = a.clamp(0.0, 1.0);
+            let _ = write!(svg, r#" fill-opacity="{a}" stroke-opacity="{a}""#);
+        }
+        if opts.data_attrs {
+            let _ = write!(svg, r#" data-index="{i}""#);
+        }
+        if opts.interactive {
+            let _ = write!(svg, "><title>{}", i + 1 + opts.number_offset);
+            if let Some(ch) = seq_chars.as_ref().and_then(|c| c.get(i)) {
+                let _ = write!(svg, ": {}", xml_escape(ch.encode_utf8(&mut [0; 4])));
+            }
+            let _ = write!(svg, "</title></{tag}>");
+        } else {
+            let _ = svg.write_str(" />");
+        }
     }
 }
 
+fn base_stroke<'a>(fill: &'a str, opts: &SvgOptions) -> &'a str {
+    if opts.monochrome { "black" } else { fill }
+}
+
 /// Determine the fill color for a base, checking per-base → per-nucleotide → uniform.
-fn get_base_fill<'a>(i: usize, seq_bytes: Option<&[u8]>, opts: &'a SvgOptions) -> &'a str {
+///
+/// `seq` holds one char per base, so a stray multi-byte character shifts neither
+/// the colors nor the labels of the bases after it.
+fn get_base_fill<'a>(i: usize, seq: Option<&[char]>, opts: &'a SvgOptions) -> &'a str {
     // Priority 1: per-base colors
     if let Some(colors) = &opts.per_base_colors {
         if i < colors.len() {
@@ -534,14 +1879,14 @@ fn get_base_fill<'a>(i: usize, seq_bytes: Option<&[u8]>, opts: &'a SvgOptions) -
         }
     }
     // Priority 2: per-nucleotide-type colors
-    if let (Some(colors), Some(sb)) = (&opts.base_colors, seq_bytes) {
-        if i < sb.len() {
-            return match sb[i] {
-                b'A' | b'a' => &colors[0],
-                b'U' | b'u' | b'T' | b't' => &colors[1],
-                b'G' | b'g' => &colors[2],
-                b'C' | b'c' => &colors[3],
-                _ => &opts.base_fill,
+    if let (Some(colors), Some(seq)) = (&opts.base_colors, seq) {
+        if i < seq.len() {
+            return match seq[i] {
+                'A' | 'a' => &colors[0],
+                'U' | 'u' | 'T' | 't' => &colors[1],
+                'G' | 'g' => &colors[2],
+                'C' | 'c' => &colors[3],
+                _ => opts.ambiguous_color.as_deref().unwrap_or(&opts.base_fill),
             };
         }
     }
@@ -549,31 +1894,159 @@ fn get_base_fill<'a>(i: usize, seq_bytes: Option<&[u8]>, opts: &'a SvgOptions) -
     &opts.base_fill
 }
 
-fn render_labels(svg: &mut String, bases: &[Base], seq: &str, scale: f64, opts: &SvgOptions) {
+/// Draw nucleotide letters. Unpaired letters sit at the base's `xt`/`yt`; paired
+/// bases, whose `xt`/`yt` is the shared pair midpoint, get the same half-unit offset
+/// outward from the helix instead so the two letters don't overlap.
+fn render_labels(
+    svg: &mut dyn Write,
+    result: &DrawResult,
+    seq: &str,
+    scale: f64,
+    opts: &SvgOptions,
+) {
+    let prec = opts.coord_precision;
     let chars: Vec<char> = seq.chars().collect();
-    for (i, b) in bases.iter().enumerate() {
+    for (i, b) in result.layout.bases.iter().enumerate() {
         if i >= chars.len() {
             break;
         }
-        let tx = b.xt * scale;
-        let ty = -b.yt * scale;
+        if !opts.in_window(i) {
+            continue;
+        }
+        let (lx, ly) = if !opts.show_bases {
+            (b.x, b.y)
+        } else if result.pairs[i] != i {
+            let (dx, dy) = crate::outward_direction(result, i);
+            (b.x + LABEL_OFFSET * dx, b.y + LABEL_OFFSET * dy)
+        } else {
+            (b.xt, b.yt)
+        };
+        let (tx, ty) = snap(lx * scale, -ly * scale, opts);
+        let _ = write!(
+            svg,
+            r#"<text x="{:.prec$}" y="{:.prec$}" font-size="{}" text-anchor="middle" dominant-baseline="central" fill="{}""#,
+            tx, ty, opts.font_size, opts.label_color
+        );
+        if let Some(outline) = &opts.label_outline {
+            let _ = write!(
+                svg,
+                r#" stroke="{}" stroke-width="{}" stroke-linejoin="round" paint-order="stroke""#,
+                outline,
+                opts.font_size / 4.0
+            );
+        }
+        let mut buf = [0; 4];
+        let _ = write!(
+            svg,
+            ">{}</text>",
+            xml_escape(chars[i].encode_utf8(&mut buf))
+        );
+    }
+}
+
+/// One cell per base across `width` starting at `x`, with 5′/3′ end labels and
+/// ticks underneath at every base number (see `number_offset`) divisible by 10.
+fn render_sequence_track(
+    svg: &mut dyn Write,
+    n: usize,
+    seq: Option<&str>,
+    x: f64,
+    y: f64,
+    width: f64,
+    opts: &SvgOptions,
+) {
+    let prec = opts.coord_precision;
+    if n == 0 {
+        return;
+    }
+    let seq_chars: Option<Vec<char>> = seq.map(|s| s.chars().collect());
+    let end_label_w = 16.0;
+    let x0 = x + end_label_w;
+    let cell_w = (width - 2.0 * end_label_w).max(n as f64) / n as f64;
+    let cell_h = 12.0;
+    let cell_y = y + 8.0;
+    let mid_y = cell_y + cell_h / 2.0;
+    let tick_font = opts.font_size * 0.8;
+
+    let _ = write!(
+        svg,
+        r#"<text x="{:.prec$}" y="{mid_y:.prec$}" font-size="{}" text-anchor="end" dominant-baseline="central">5′</text>"#,
+        x0 - 4.0,
+        opts.font_size
+    );
+    for i in 0..n {
+        let fill = get_base_fill(i, seq_chars.as_deref(), opts);
         let _ = write!(
             svg,
-            r#"<text x="{:.2}" y="{:.2}" font-size="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
-            tx, ty, opts.font_size, chars[i]
+            r#"<rect x="{:.prec$}" y="{cell_y:.prec$}" width="{cell_w:.prec$}" height="{cell_h}" fill="{fill}" stroke="{}" stroke-width="0.5" />"#,
+            x0 + i as f64 * cell_w,
+            base_stroke(fill, opts)
         );
+        let number = i + 1 + opts.number_offset;
+        if number.is_multiple_of(10) {
+            let tx = x0 + (i as f64 + 0.5) * cell_w;
+            let _ = write!(
+                svg,
+                r#"<line x1="{tx:.prec$}" y1="{:.prec$}" x2="{tx:.prec$}" y2="{:.prec$}" stroke-width="1" stroke="black" /><text x="{tx:.prec$}" y="{:.prec$}" font-size="{tick_font}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                cell_y + cell_h,
+                cell_y + cell_h + 4.0,
+                cell_y + cell_h + 10.0,
+                number
+            );
+        }
     }
+    let _ = write!(
+        svg,
+        r#"<text x="{:.prec$}" y="{mid_y:.prec$}" font-size="{}" text-anchor="start" dominant-baseline="central">3′</text>"#,
+        x0 + n as f64 * cell_w + 4.0,
+        opts.font_size
+    );
+}
+
+/// Render only the legend selected by `opts` as a standalone SVG `height` px tall,
+/// e.g. to share one legend across several structures in a figure.
+///
+/// Probabilities or `position_gradient` select their legend as in `render`.
+/// With no legend the SVG is empty and zero-width.
+pub fn render_legend_svg(opts: &SvgOptions, height: f64) -> String {
+    let opts = resolve_options(opts, 0);
+    let mut svg = String::with_capacity(2048);
+    write_svg_open(
+        &mut svg,
+        0.0,
+        0.0,
+        legend_width(&opts.legend),
+        height,
+        &opts,
+    );
+    write_pattern_defs(&mut svg, &opts);
+    render_legend(&mut svg, 0.0, 0.0, height, &opts);
+    svg.push_str("</svg>");
+    svg
 }
 
-fn render_legend(svg: &mut String, x: f64, vb_y: f64, vb_h: f64, opts: &SvgOptions) {
-    match opts.legend {
-        Legend::None => {}
-        Legend::Nucleotide => render_nucleotide_legend(svg, x, vb_y, vb_h, opts),
-        Legend::Probability => render_probability_legend(svg, x, vb_y, vb_h),
+/// Horizontal space reserved for a legend to the right of the structure.
+fn legend_width(legend: &Legend) -> f64 {
+    match legend {
+        Legend::None => 0.0,
+        Legend::Nucleotide => 80.0,
+        Legend::Probability => 100.0,
+        Legend::Position => 80.0,
     }
 }
 
-fn render_nucleotide_legend(svg: &mut String, x: f64, vb_y: f64, vb_h: f64, opts: &SvgOptions) {
+fn render_legend(svg: &mut dyn Write, x: f64, vb_y: f64, vb_h: f64, opts: &SvgOptions) {
+    let draw: fn(&mut dyn Write, f64, f64, f64, &SvgOptions) = match opts.legend {
+        Legend::None => return,
+        Legend::Nucleotide => render_nucleotide_legend,
+        Legend::Probability => render_probability_legend,
+        Legend::Position => render_position_legend,
+    };
+    layer(svg, "legend", opts, |svg| draw(svg, x, vb_y, vb_h, opts));
+}
+
+fn render_nucleotide_legend(svg: &mut dyn Write, x: f64, vb_y: f64, vb_h: f64, opts: &SvgOptions) {
+    let prec = opts.coord_precision;
     let colors = opts
         .base_colors
         .as_ref()
@@ -589,33 +2062,132 @@ fn render_nucleotide_legend(svg: &mut String, x: f64, vb_y: f64, vb_h: f64, opts
     let start_y = vb_y + (vb_h - total_h) / 2.0;
     let cx = x + 10.0 + r;
 
+    if let Some(title) = &opts.legend_title {
+        let _ = write!(
+            svg,
+            r#"<text x="{:.prec$}" y="{:.prec$}" font-family="sans-serif" font-size="{font_size}" dominant-baseline="central">{}</text>"#,
+            x + 10.0,
+            start_y - font_size,
+            xml_escape(title)
+        );
+    }
     for (row, &label) in labels.iter().enumerate() {
         let cy = start_y + row as f64 * row_height + r;
         let fill = colors[color_idx[row]];
         let _ = write!(
             svg,
-            r#"<circle r="{r}" cx="{cx:.2}" cy="{cy:.2}" fill="{fill}" stroke-width="{sw}" stroke="{fill}" />"#,
+            r#"<circle r="{r}" cx="{cx:.prec$}" cy="{cy:.prec$}" fill="{fill}" stroke-width="{sw}" stroke="{stroke}" />"#,
             sw = opts.base_stroke_width,
+            stroke = base_stroke(fill, opts),
         );
         let _ = write!(
             svg,
-            r#"<text x="{tx:.2}" y="{cy:.2}" font-family="sans-serif" font-size="{font_size}" dominant-baseline="central">{label}</text>"#,
+            r#"<text x="{tx:.prec$}" y="{cy:.prec$}" font-family="sans-serif" font-size="{font_size}" dominant-baseline="central">{label}</text>"#,
             tx = cx + r + 8.0,
         );
     }
 }
 
-fn render_probability_legend(svg: &mut String, x: f64, vb_y: f64, vb_h: f64) {
+fn render_probability_legend(svg: &mut dyn Write, x: f64, vb_y: f64, vb_h: f64, opts: &SvgOptions) {
+    let ticks: Vec<(f64, String)> = match opts.value_range {
+        // Five ticks over the value range; diverging puts zero in the middle
+        Some((lo, hi)) => (0..=4)
+            .map(|i| {
+                let t = i as f64 / 4.0;
+                let val = match (opts.diverging, i) {
+                    (true, 0..=2) => lo * (1.0 - t * 2.0),
+                    (true, _) => hi * (t * 2.0 - 1.0),
+                    (false, _) => lo + (hi - lo) * t,
+                };
+                (t, format_tick(val))
+            })
+            .collect(),
+        // Tick labels in steps of a tenth: 0.0 to 1.0, or 0 to 100 for percentages
+        None => (0..=10)
+            .map(|i| {
+                let val = i as f64 / 10.0;
+                let text = match opts.probability_scale {
+                    ProbScale::Unit => format!("{val:.1}"),
+                    ProbScale::Percent => format!("{}", i * 10),
+                };
+                (val, text)
+            })
+            .collect(),
+    };
+    let default_title = if opts.value_range.is_some() {
+        "Value"
+    } else {
+        "Equilibrium probability"
+    };
+    let bar = Colorbar {
+        grad_id: "prob-grad",
+        ticks: &ticks,
+        label_gap: 35.0,
+        label: &xml_escape(opts.legend_title.as_deref().unwrap_or(default_title)),
+    };
+    render_colorbar(svg, x, vb_y, vb_h, &bar, opts);
+}
+
+/// Legend tick label with at most two decimals and no trailing zeros.
+fn format_tick(v: f64) -> String {
+    let s = format!("{:.2}", v + 0.0);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".into() } else { s.into() }
+}
+
+fn render_position_legend(svg: &mut dyn Write, x: f64, vb_y: f64, vb_h: f64, opts: &SvgOptions) {
+    let ticks = [(0.0, "5′".to_string()), (1.0, "3′".to_string())];
+    let bar = Colorbar {
+        grad_id: "pos-grad",
+        ticks: &ticks,
+        label_gap: 20.0,
+        label: &xml_escape(opts.legend_title.as_deref().unwrap_or("5′ → 3′")),
+    };
+    render_colorbar(svg, x, vb_y, vb_h, &bar, opts);
+}
+
+/// Content of a colorbar legend, see `render_colorbar`.
+struct Colorbar<'a> {
+    grad_id: &'a str,
+    /// Tick labels at fractions (0.0 = bottom, 1.0 = top)
+    ticks: &'a [(f64, String)],
+    /// Distance (px) of the rotated title right of the ticks
+    label_gap: f64,
+    /// Title, already XML-escaped
+    label: &'a str,
+}
+
+/// Vertical gradient bar over `opts.colormap` with the ticks and rotated title
+/// of `bar`.
+fn render_colorbar(
+    svg: &mut dyn Write,
+    x: f64,
+    vb_y: f64,
+    vb_h: f64,
+    bar: &Colorbar,
+    opts: &SvgOptions,
+) {
+    let Colorbar {
+        grad_id,
+        ticks,
+        label_gap,
+        label,
+    } = *bar;
+    let prec = opts.coord_precision;
     let bar_w = 20.0;
     let bar_h = vb_h * 0.6;
     let bar_x = x + 10.0;
     let bar_y = vb_y + (vb_h - bar_h) / 2.0;
-    let n_stops = PROB_COLORMAP.len();
+    let stops = opts.colormap.stops();
+    let n_stops = stops.len();
     let font_size = 12.0;
 
     // Gradient definition
-    svg.push_str(r#"<defs><linearGradient id="prob-grad" x1="0" y1="0" x2="0" y2="1">"#);
-    for (i, &(r, g, b)) in PROB_COLORMAP.iter().rev().enumerate() {
+    let _ = write!(
+        svg,
+        r#"<defs><linearGradient id="{grad_id}" x1="0" y1="0" x2="0" y2="1">"#
+    );
+    for (i, &(r, g, b)) in stops.iter().rev().enumerate() {
         let offset = i as f64 / (n_stops - 1) as f64 * 100.0;
         let ri = (r * 255.0) as u8;
         let gi = (g * 255.0) as u8;
@@ -625,31 +2197,29 @@ fn render_probability_legend(svg: &mut String, x: f64, vb_y: f64, vb_h: f64) {
             "<stop offset=\"{offset:.1}%\" stop-color=\"#{ri:02x}{gi:02x}{bi:02x}\"/>",
         );
     }
-    svg.push_str("</linearGradient></defs>");
+    let _ = svg.write_str("</linearGradient></defs>");
 
     // Color bar
     let _ = write!(
         svg,
-        r#"<rect x="{bar_x:.2}" y="{bar_y:.2}" width="{bar_w}" height="{bar_h:.2}" fill="url(#prob-grad)" stroke="none"/>"#,
+        r#"<rect x="{bar_x:.prec$}" y="{bar_y:.prec$}" width="{bar_w}" height="{bar_h:.prec$}" fill="url(#{grad_id})" stroke="none"/>"#,
     );
 
-    // Tick labels: 0.0 to 1.0 in steps of 0.1
     let text_x = bar_x + bar_w + 5.0;
-    for i in 0..=10 {
-        let val = i as f64 / 10.0;
+    for (val, text) in ticks {
         let ty = bar_y + bar_h * (1.0 - val);
         let _ = write!(
             svg,
-            r#"<text x="{text_x:.2}" y="{ty:.2}" font-family="sans-serif" font-size="{font_size}" dominant-baseline="central">{val:.1}</text>"#,
+            r#"<text x="{text_x:.prec$}" y="{ty:.prec$}" font-family="sans-serif" font-size="{font_size}" dominant-baseline="central">{text}</text>"#,
         );
     }
 
     // Rotated label
-    let label_x = text_x + 35.0;
+    let label_x = text_x + label_gap;
     let label_y = bar_y + bar_h / 2.0;
     let _ = write!(
         svg,
-        r#"<text x="{label_x:.2}" y="{label_y:.2}" font-family="sans-serif" font-size="{font_size}" text-anchor="middle" dominant-baseline="central" transform="rotate(90,{label_x:.2},{label_y:.2})">Equilibrium probability</text>"#,
+        r#"<text x="{label_x:.prec$}" y="{label_y:.prec$}" font-family="sans-serif" font-size="{font_size}" text-anchor="middle" dominant-baseline="central" transform="rotate(90,{label_x:.prec$},{label_y:.prec$})">{label}</text>"#,
     );
 }
 
@@ -670,6 +2240,7 @@ mod tests {
     #[test]
     fn test_arc_90_degrees_ccw() {
         let arc = ArcSegment {
+            ellipse: None,
             x: 0.0,
             y: 0.0,
             r: 1.0,
@@ -678,7 +2249,7 @@ mod tests {
         };
         let mut svg = String::new();
         let opts = SvgOptions::default();
-        render_individual_segment(&mut svg, &Segment::Arc(arc), 50.0, &opts);
+        render_individual_segment(&mut svg, &Segment::Arc(arc), 50.0, "black", &opts);
         assert!(svg.contains("A50.00 50.00 0 0 0"));
         assert!(svg.contains("0.00 -50.00"));
     }
@@ -686,6 +2257,7 @@ mod tests {
     #[test]
     fn test_arc_180_degrees() {
         let arc = ArcSegment {
+            ellipse: None,
             x: 0.0,
             y: 0.0,
             r: 1.0,
@@ -694,13 +2266,14 @@ mod tests {
         };
         let mut svg = String::new();
         let opts = SvgOptions::default();
-        render_individual_segment(&mut svg, &Segment::Arc(arc), 50.0, &opts);
+        render_individual_segment(&mut svg, &Segment::Arc(arc), 50.0, "black", &opts);
         assert!(svg.contains("A50.00 50.00 0 0 0"));
     }
 
     #[test]
     fn test_arc_cw_direction() {
         let arc = ArcSegment {
+            ellipse: None,
             x: 0.0,
             y: 0.0,
             r: 1.0,
@@ -709,13 +2282,40 @@ mod tests {
         };
         let mut svg = String::new();
         let opts = SvgOptions::default();
-        render_individual_segment(&mut svg, &Segment::Arc(arc), 50.0, &opts);
+        render_individual_segment(&mut svg, &Segment::Arc(arc), 50.0, "black", &opts);
         assert!(svg.contains("0 0 1"));
     }
 
+    #[test]
+    fn test_arc_svg_params() {
+        let arc = |t1: f64, t2: f64| ArcSegment {
+            ellipse: None,
+            x: 1.0,
+            y: 0.0,
+            r: 1.0,
+            t1,
+            t2,
+        };
+        let close = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1) < 1e-12;
+
+        // CCW in layout space is a negative-angle (sweep 0) arc once y is flipped
+        let (start, end, large_arc, sweep) = arc_svg_params(&arc(PI / 2.0, 0.0));
+        assert!(close(start, (2.0, 0.0)) && close(end, (1.0, -1.0)));
+        assert_eq!((large_arc, sweep), (false, false));
+
+        let (start, end, large_arc, sweep) = arc_svg_params(&arc(0.0, PI / 2.0));
+        assert!(close(start, (1.0, -1.0)) && close(end, (2.0, 0.0)));
+        assert_eq!((large_arc, sweep), (false, true));
+
+        // A 270° CCW span is drawn the short way round, clockwise
+        let (_, _, large_arc, sweep) = arc_svg_params(&arc(3.0 * PI / 2.0, 0.0));
+        assert_eq!((large_arc, sweep), (false, true));
+    }
+
     #[test]
     fn test_degenerate_arc_skipped() {
         let arc = ArcSegment {
+            ellipse: None,
             x: 0.0,
             y: 0.0,
             r: 1.0,
@@ -724,7 +2324,7 @@ mod tests {
         };
         let mut svg = String::new();
         let opts = SvgOptions::default();
-        render_individual_segment(&mut svg, &Segment::Arc(arc), 50.0, &opts);
+        render_individual_segment(&mut svg, &Segment::Arc(arc), 50.0, "black", &opts);
         assert!(svg.is_empty());
     }
 
@@ -774,6 +2374,64 @@ mod tests {
         assert!(svg.contains(">C<"));
     }
 
+    #[test]
+    fn test_paired_labels_do_not_overlap() {
+        let opts = SvgOptions {
+            show_labels: true,
+            align_stem: false,
+            ..SvgOptions::default()
+        };
+        let r = crate::layout("((...))").unwrap();
+        let svg = render(&r, Some("GCAAAGC"), &opts);
+        let positions: Vec<(f64, f64)> = svg
+            .split("<text x=\"")
+            .skip(1)
+            .map(|t| {
+                let x: f64 = t[..t.find('"').unwrap()].parse().unwrap();
+                let t = &t[t.find("y=\"").unwrap() + 3..];
+                (x, t[..t.find('"').unwrap()].parse().unwrap())
+            })
+            .collect();
+        assert_eq!(positions.len(), 7);
+        // Paired letters 25px (0.5 units) out from their own base, away from the partner
+        let (b0, b6) = (&r.layout.bases[0], &r.layout.bases[6]);
+        let (x0, y0) = positions[0];
+        assert!(((x0 - b0.x * 50.0).hypot(y0 + b0.y * 50.0) - 25.0).abs() < 0.01);
+        assert!((x0 - b6.x * 50.0).hypot(y0 + b6.y * 50.0) > 50.0);
+        for i in 0..7 {
+            for j in i + 1..7 {
+                let (a, b) = (positions[i], positions[j]);
+                assert!((a.0 - b.0).hypot(a.1 - b.1) > 10.0, "labels {i} and {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_label_color_and_outline() {
+        let opts = SvgOptions {
+            show_labels: true,
+            label_color: "white".into(),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("(.)", Some("GAC"), &opts);
+        assert_eq!(svg.matches(r#"fill="white">"#).count(), 3);
+        assert!(!svg.contains("paint-order"));
+
+        let opts = SvgOptions {
+            show_labels: true,
+            label_outline: Some("black".into()),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("(.)", Some("GAC"), &opts);
+        assert_eq!(
+            svg.matches(
+                r#"stroke="black" stroke-width="2.5" stroke-linejoin="round" paint-order="stroke">"#
+            )
+            .count(),
+            3
+        );
+    }
+
     #[test]
     fn test_draw_svg_nucleotide_colors() {
         let opts = SvgOptions {
@@ -879,6 +2537,40 @@ mod tests {
         assert!(svg.contains("<marker"));
     }
 
+    #[test]
+    fn test_arrows_as_paths() {
+        let opts = SvgOptions {
+            arrows_as_paths: true,
+            strand_colors: Some(vec!["red".into(), "blue".into()]),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((((+))))", None, &opts);
+        assert!(!svg.contains("marker"));
+        assert!(!svg.contains("arrowblack"));
+        assert_eq!(svg.matches(r#"Z" fill="red" />"#).count(), 1);
+        assert_eq!(svg.matches(r#"Z" fill="blue" />"#).count(), 1);
+
+        // Same size as the marker: 3 × backbone_width across
+        let svg = crate::draw_svg(
+            "(((...)))",
+            None,
+            &SvgOptions {
+                arrows_as_paths: true,
+                ..SvgOptions::default()
+            },
+        );
+        let head = svg.split(r#"<path d="M"#).last().unwrap();
+        let pts: Vec<f64> = head[..head.find('Z').unwrap()]
+            .split(' ')
+            .map(|v| v.parse().unwrap())
+            .collect();
+        let d =
+            |a: usize, b: usize| (pts[2 * a] - pts[2 * b]).hypot(pts[2 * a + 1] - pts[2 * b + 1]);
+        // (0,0) to (0,20) and (10,0) to (10,20) in marker units
+        assert!((d(0, 4) - 15.0).abs() < 0.02);
+        assert!((d(1, 3) - 15.0).abs() < 0.02);
+    }
+
     #[test]
     fn test_no_arrows() {
         let opts = SvgOptions {
@@ -891,7 +2583,1068 @@ mod tests {
     }
 
     #[test]
-    fn test_default_nucleotide_preset() {
-        assert_eq!(DEFAULT_NUCLEOTIDE_COLORS, ["green", "red", "black", "blue"]);
+    fn test_position_gradient() {
+        let opts = SvgOptions {
+            position_gradient: true,
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("(((...)))", None, &opts);
+        // First base gets the low end of the colormap, last base the high end
+        assert!(svg.contains(&format!(r#"fill="{}""#, probability_to_color(0.0))));
+        assert!(svg.contains(&format!(r#"fill="{}""#, probability_to_color(1.0))));
+        assert!(svg.contains("pos-grad"));
+        assert!(svg.contains("5′ → 3′"));
+    }
+
+    #[test]
+    fn test_non_ascii_sequence_keeps_base_order() {
+        let opts = SvgOptions {
+            base_colors: Some(DEFAULT_NUCLEOTIDE_COLORS.map(String::from)),
+            interactive: true,
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((.))", Some("GGΨCC"), &opts);
+        assert_eq!(svg.matches(r#"fill="blue" stroke-width"#).count(), 2);
+        assert!(svg.contains("<title>3: Ψ</title></circle>"));
+        assert!(svg.contains("<title>4: C</title></circle>"));
+    }
+
+    #[test]
+    fn test_interactive_titles() {
+        let opts = SvgOptions {
+            interactive: true,
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((..))", Some("GACUGC"), &opts);
+        assert_eq!(svg.matches("<title>").count(), 6);
+        assert!(svg.contains("<title>1: G</title></circle>"));
+        assert!(svg.contains("<title>6: C</title></circle>"));
+
+        // Without a sequence only the index is shown
+        let svg = crate::draw_svg("((..))", None, &opts);
+        assert!(svg.contains("<title>3</title></circle>"));
+
+        let opts = SvgOptions {
+            number_offset: 500,
+            ..opts
+        };
+        let svg = crate::draw_svg("((..))", None, &opts);
+        assert!(svg.contains("<title>501</title></circle>"));
+
+        // Sequence characters are escaped
+        let opts = SvgOptions {
+            show_labels: true,
+            ..opts
+        };
+        let svg = crate::draw_svg("((..))", Some("G<>UGC"), &opts);
+        assert!(svg.contains("<title>502: &lt;</title>"));
+        assert!(svg.contains("<title>503: &gt;</title>"));
+        assert!(svg.contains(">&lt;</text>") && !svg.contains("><</text>"));
+    }
+
+    #[test]
+    fn test_snap_pixels() {
+        let opts = SvgOptions {
+            snap_pixels: true,
+            show_labels: true,
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((.+.))", Some("GGAACC"), &opts);
+        // Every coordinate attribute is a multiple of 0.5
+        for attr in ["x1", "y1", "x2", "y2", "cx", "cy", "x", "y"] {
+            let pat = format!(" {attr}=\"");
+            for part in svg.split(&pat).skip(1) {
+                let v: f64 = part[..part.find('"').unwrap()].parse().unwrap();
+                assert_eq!((v * 2.0).fract(), 0.0, "{attr}={v} not snapped");
+            }
+        }
+    }
+
+    #[test]
+    fn test_colormaps() {
+        assert_eq!(Colormap::Equilibrium.color(0.3), probability_to_color(0.3));
+        assert_eq!(Colormap::Grayscale.color(1.0), "#000000");
+        assert_eq!(Colormap::Viridis.color(0.0), "#440153");
+
+        let opts = SvgOptions {
+            probabilities: Some(vec![1.0; 6]),
+            colormap: Colormap::Grayscale,
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((..))", None, &opts);
+        assert!(svg.contains(r##"fill="#000000""##));
+        // Legend gradient uses the selected colormap
+        assert!(svg.contains(r##"stop-color="#000000""##));
+        assert!(!svg.contains(&probability_to_color(1.0)));
+    }
+
+    #[test]
+    fn test_data_attrs() {
+        let svg = crate::draw_svg("((..))", None, &SvgOptions::default());
+        assert!(!svg.contains("data-"));
+
+        let opts = SvgOptions {
+            data_attrs: true,
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((..))", None, &opts);
+        assert_eq!(svg.matches("data-index=").count(), 6);
+        assert!(svg.contains(r#"data-index="5" />"#));
+        assert!(svg.contains(r#"data-i="0" data-j="5" />"#));
+        assert!(svg.contains(r#"data-i="1" data-j="4" />"#));
+    }
+
+    #[test]
+    fn test_render_tiles() {
+        let r = crate::layout("((((....))))").unwrap();
+        let opts = SvgOptions {
+            legend: Legend::Position,
+            position_gradient: true,
+            ..SvgOptions::default()
+        };
+        assert!(render_tiles(&r, None, &opts, 0, 2).is_empty());
+
+        let full = render(&r, None, &opts);
+        let tiles = render_tiles(&r, None, &opts, 2, 3);
+        assert_eq!(tiles.len(), 6);
+
+        let view_box = |svg: &str| -> Vec<f64> {
+            let start = svg.find("viewBox=\"").unwrap() + 9;
+            let end = start + svg[start..].find('"').unwrap();
+            svg[start..end]
+                .split(' ')
+                .map(|v| v.parse().unwrap())
+                .collect()
+        };
+        let [fx, fy, fw, fh] = view_box(&full)[..] else {
+            panic!()
+        };
+        let struct_w = fw - 80.0;
+        let (tw, th) = (struct_w / 3.0, fh / 2.0);
+
+        // Legend only on the first tile, which is widened to fit it
+        assert!(tiles[0].contains("pos-grad"));
+        assert!(tiles[1..].iter().all(|t| !t.contains("pos-grad")));
+        let vb = view_box(&tiles[0]);
+        assert!((vb[0] - fx).abs() < 0.01 && (vb[1] - fy).abs() < 0.01);
+        assert!((vb[2] - (tw + 80.0)).abs() < 0.01);
+
+        // Last tile is the bottom-right corner of the structure area
+        let vb = view_box(&tiles[5]);
+        assert!((vb[0] - (fx + 2.0 * tw)).abs() < 0.01);
+        assert!((vb[1] - (fy + th)).abs() < 0.01);
+        assert!((vb[2] - tw).abs() < 0.01 && (vb[3] - th).abs() < 0.01);
+
+        // Every tile carries the whole drawing
+        let circles = full.matches("<circle").count();
+        assert!(
+            tiles[1..]
+                .iter()
+                .all(|t| t.matches("<circle").count() == circles)
+        );
+    }
+
+    #[test]
+    fn test_hide_bases() {
+        let opts = SvgOptions {
+            show_bases: false,
+            show_arrows: false,
+            show_labels: true,
+            ..SvgOptions::default()
+        };
+        let r = crate::layout("((...))").unwrap();
+        let svg = render(&r, Some("GGAAACC"), &opts);
+        assert!(!svg.contains("<circle"));
+        // Labels sit on the base positions
+        let b = &r.layout.bases[2];
+        let pos = format!(r#"<text x="{:.2}" y="{:.2}""#, b.x * 50.0, -b.y * 50.0);
+        assert!(svg.contains(&pos), "{pos}");
+
+        // Bounding box shrinks to the backbone, but still covers every base
+        let shown = render(&r, None, &SvgOptions::default());
+        let width = |svg: &str| -> f64 {
+            let vb = &svg[svg.find("viewBox=\"").unwrap() + 9..];
+            vb.split(' ').nth(2).unwrap().parse().unwrap()
+        };
+        assert!(width(&svg) < width(&shown));
+        assert!(width(&svg) > 0.0);
+    }
+
+    #[test]
+    fn test_sequence_track() {
+        let input = "((((......))))";
+        let seq = "GGGAAUUCCACCCC";
+        let plain = crate::draw_svg(input, Some(seq), &SvgOptions::default());
+        let opts = SvgOptions {
+            sequence_track: true,
+            base_colors: Some(DEFAULT_NUCLEOTIDE_COLORS.map(String::from)),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg(input, Some(seq), &opts);
+
+        let height = |svg: &str| -> f64 {
+            let vb = &svg[svg.find("viewBox=\"").unwrap() + 9..];
+            vb[..vb.find('"').unwrap()]
+                .split(' ')
+                .nth(3)
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+        assert!((height(&svg) - height(&plain) - TRACK_HEIGHT).abs() < 0.01);
+        assert_eq!(svg.matches("<rect").count(), 14);
+        // Track cells use the same nucleotide fills as the markers (6 C's)
+        assert_eq!(
+            svg.matches(r#"fill="blue" stroke="blue" stroke-width="0.5""#)
+                .count(),
+            6
+        );
+        assert_eq!(svg.matches(r#"fill="blue" stroke-width="2.5""#).count(), 6);
+        assert!(svg.contains(">5′</text>") && svg.contains(">3′</text>"));
+        assert!(svg.contains(">10</text>"));
+    }
+
+    #[test]
+    fn test_ambiguous_color() {
+        let mut opts = SvgOptions {
+            base_colors: Some(DEFAULT_NUCLEOTIDE_COLORS.map(String::from)),
+            base_fill: "gray".into(),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((...))", Some("GNAARYC"), &opts);
+        assert_eq!(svg.matches(r#"fill="gray""#).count(), 3);
+
+        opts.ambiguous_color = Some("orange".into());
+        let svg = crate::draw_svg("((...))", Some("GNAARYC"), &opts);
+        assert_eq!(svg.matches(r#"fill="orange""#).count(), 3);
+        assert!(!svg.contains(r#"fill="gray""#));
+
+        // Only applies to nucleotide coloring
+        opts.base_colors = None;
+        let svg = crate::draw_svg("((...))", Some("GNAARYC"), &opts);
+        assert!(!svg.contains("orange"));
+    }
+
+    #[test]
+    fn test_merge_backbone() {
+        let r = crate::layout("((...))+((..))").unwrap();
+        let opts = SvgOptions {
+            merge_backbone: true,
+            show_arrows: false,
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        let paths: Vec<&str> = svg
+            .split(r#"<path d=""#)
+            .skip(1)
+            .map(|p| &p[..p.find('"').unwrap()])
+            .collect();
+        assert_eq!(paths.len(), 2);
+        assert!(!svg.contains(r#"stroke-opacity="1""#), "no per-piece lines");
+
+        // The first strand's path runs from base 0 through every base of it,
+        // with arcs around the hairpin
+        let at = |k: usize| {
+            let b = &r.layout.bases[k];
+            format!("{:.2} {:.2}", b.x * 50.0, -b.y * 50.0)
+        };
+        assert!(paths[0].starts_with(&format!("M{} ", at(0))));
+        for k in 1..7 {
+            assert!(paths[0].contains(&at(k)), "base {k} not on the path");
+        }
+        assert!(paths[0].contains(" A"));
+    }
+
+    #[test]
+    fn test_focus() {
+        let r = crate::layout("((...))((...))").unwrap();
+        let opts = SvgOptions {
+            focus: Some((7, 13)),
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        // Everything is drawn, the bases outside the focus faded
+        assert_eq!(svg.matches("<circle").count(), 14);
+        assert_eq!(svg.matches(r#"fill-opacity="0.3""#).count(), 7);
+
+        // Framed like a window over the same bases
+        let window = SvgOptions {
+            window: Some((7, 13)),
+            ..SvgOptions::default()
+        };
+        let view_box = |svg: &str| {
+            let vb = &svg[svg.find("viewBox=\"").unwrap() + 9..];
+            vb[..vb.find('"').unwrap()].to_string()
+        };
+        assert_eq!(view_box(&svg), view_box(&render(&r, None, &window)));
+    }
+
+    #[test]
+    fn test_window() {
+        // Hairpin (0..=6) followed by a second hairpin (7..=13)
+        let r = crate::layout("((...))((...))").unwrap();
+        let opts = SvgOptions {
+            window: Some((7, 13)),
+            data_attrs: true,
+            show_labels: true,
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, Some("GGAAACCGGAAACC"), &opts);
+        assert_eq!(svg.matches("data-index=").count(), 7);
+        assert!(!svg.contains(r#"data-index="6""#));
+        assert_eq!(svg.matches("<text").count(), 7);
+        assert!(svg.contains(r#"data-i="7" data-j="13" />"#));
+        assert!(!svg.contains(r#"data-i="0""#));
+        // 6 links inside the window, each drawn as two halves
+        let backbone = svg.matches(r#"stroke-width="5""#).count();
+        assert_eq!(backbone, 12 + 1, "backbone halves plus the 3' arrow");
+
+        // Framed to the window's bases only
+        let full = render(&r, None, &SvgOptions::default());
+        let width = |svg: &str| -> f64 {
+            let vb = &svg[svg.find("viewBox=\"").unwrap() + 9..];
+            vb.split(' ').nth(2).unwrap().parse().unwrap()
+        };
+        assert!(width(&svg) < width(&full));
+
+        // A bond leaving the window is faded
+        let r = crate::layout("((...))").unwrap();
+        let opts = SvgOptions {
+            window: Some((0, 3)),
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        assert_eq!(svg.matches(r#"opacity="0.3""#).count(), 2);
+    }
+
+    #[test]
+    fn test_ring_colors() {
+        let opts = SvgOptions {
+            probabilities: Some(vec![1.0; 4]),
+            per_base_ring_colors: Some(vec!["orange".into(), "purple".into()]),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("(..)", None, &opts);
+        let fill = probability_to_color(1.0);
+        assert!(svg.contains(&format!(
+            r#"fill="{fill}" stroke-width="2.5" stroke="orange""#
+        )));
+        assert!(svg.contains(&format!(
+            r#"fill="{fill}" stroke-width="2.5" stroke="purple""#
+        )));
+        // Bases past the list keep a ring matching the fill
+        assert_eq!(
+            svg.matches(&format!(
+                r#"fill="{fill}" stroke-width="2.5" stroke="{fill}""#
+            ))
+            .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_render_with_map() {
+        let r = crate::layout("((.+.))").unwrap();
+        let opts = SvgOptions {
+            data_attrs: true,
+            ..SvgOptions::default()
+        };
+        let (svg, centers) = render_with_map(&r, None, &opts);
+        assert_eq!(svg, render(&r, None, &opts));
+        assert_eq!(centers.len(), 6);
+        for (i, (x, y)) in centers.iter().enumerate() {
+            let marker = format!(r#"cx="{x:.2}" cy="{y:.2}""#);
+            let at = svg.find(&marker).expect("marker at mapped center");
+            let element = &svg[at..at + svg[at..].find("/>").unwrap()];
+            assert!(
+                element.contains(&format!(r#"data-index="{i}""#)),
+                "{element}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_auto_stroke() {
+        let r = crate::layout("((...))").unwrap();
+        let opts = SvgOptions {
+            scale: 100.0,
+            auto_stroke: true,
+            backbone_width: 1.0,
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        assert!(svg.contains(r#"stroke-width="10" stroke="black""#));
+        assert!(svg.contains(r#"stroke-linecap="round" stroke-width="5" stroke="black""#));
+        assert!(svg.contains(r#"<circle r="15""#));
+
+        // At the default scale it matches the default widths
+        let auto = SvgOptions {
+            auto_stroke: true,
+            ..SvgOptions::default()
+        };
+        assert_eq!(
+            render(&r, None, &auto),
+            render(&r, None, &SvgOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_per_base_override() {
+        let r = crate::layout("((...))").unwrap();
+        let probs = vec![1.0; 7];
+        let manual = vec!["gray".to_string(), String::new(), "gray".to_string()];
+        let opts = SvgOptions {
+            probabilities: Some(probs.clone()),
+            per_base_colors: Some(manual.clone()),
+            ..SvgOptions::default()
+        };
+        // Probabilities win by default
+        assert!(!render(&r, None, &opts).contains(r#"fill="gray""#));
+
+        let merged = SvgOptions {
+            per_base_override: true,
+            ..opts
+        };
+        let svg = render(&r, None, &merged);
+        assert_eq!(svg.matches(r#"fill="gray""#).count(), 2);
+        let hot = Colormap::Equilibrium.color(1.0);
+        assert_eq!(svg.matches(&format!(r#"fill="{hot}""#)).count(), 5);
+    }
+
+    #[test]
+    fn test_embed_source() {
+        let r = crate::layout("((.+.))").unwrap();
+        assert!(!render(&r, None, &SvgOptions::default()).contains("<metadata>"));
+
+        let opts = SvgOptions {
+            embed_source: true,
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, Some("GA+AC"), &opts);
+        assert!(svg.contains(r#"structure="((.+.))" sequence="GA+AC"/></metadata>"#));
+
+        // draw_svg keeps the input notation, escaped
+        let svg = crate::draw_svg("<(..)>", None, &opts);
+        assert!(svg.contains(r#"structure="&lt;(..)&gt;"/></metadata>"#));
+    }
+
+    #[test]
+    fn test_render_skeleton() {
+        let r = crate::layout("((.+.))").unwrap();
+        let svg = render_skeleton(&r, &SvgOptions::default());
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert_eq!(svg.matches("<line").count(), 2);
+        assert!(!svg.contains("<circle") && !svg.contains("<path"));
+
+        let (x, y) = (r.layout.bases[3].x * 50.0, -r.layout.bases[3].y * 50.0);
+        assert!(svg.contains(&format!(r#"<polyline points="{x:.2},{y:.2} "#)));
+    }
+
+    #[test]
+    fn test_base_shapes() {
+        let r = crate::layout("((.))").unwrap();
+        let opts = SvgOptions {
+            base_shape: BaseShape::Square,
+            per_base_shape: Some(vec![BaseShape::Circle, BaseShape::Diamond]),
+            interactive: true,
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        assert_eq!(svg.matches("</title></circle>").count(), 1);
+        assert_eq!(svg.matches("</title></polygon>").count(), 1);
+        assert_eq!(svg.matches("</title></rect>").count(), 3);
+
+        // Square side spans the marker diameter, centered on the base
+        let (cx, cy) = (r.layout.bases[2].x * 50.0, -r.layout.bases[2].y * 50.0);
+        assert!(svg.contains(&format!(
+            r#"<rect x="{:.2}" y="{:.2}" width="15" height="15""#,
+            cx - 7.5,
+            cy - 7.5
+        )));
+    }
+
+    #[test]
+    fn test_emphasized_pairs() {
+        let r = crate::layout("((..((...))..))").unwrap();
+        assert!(!render(&r, None, &SvgOptions::default()).contains("opacity=\"0.3\""));
+
+        let opts = SvgOptions {
+            pair_color: "purple".into(),
+            emphasized_pairs: Some(vec![(0, 14), (10, 4)]),
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        assert_eq!(svg.matches(r#"stroke="purple" />"#).count(), 2);
+        assert_eq!(
+            svg.matches(r#"stroke="purple" opacity="0.3" />"#).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_aria_label() {
+        let r = crate::layout("((...))").unwrap();
+        assert!(!render(&r, None, &SvgOptions::default()).contains("aria-label"));
+
+        let opts = SvgOptions {
+            aria_label: Some(String::new()),
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        let open = &svg[..svg.find('>').unwrap()];
+        assert!(open.ends_with(r#" role="img" aria-label="RNA secondary structure diagram""#));
+
+        let opts = SvgOptions {
+            aria_label: Some("tRNA <Phe>".into()),
+            ..SvgOptions::default()
+        };
+        assert!(render(&r, None, &opts).contains(r#"aria-label="tRNA &lt;Phe&gt;">"#));
+    }
+
+    #[test]
+    fn test_preserve_aspect_ratio() {
+        let r = crate::layout("((...))").unwrap();
+        let svg = render(&r, None, &SvgOptions::default());
+        assert!(!svg.contains("preserveAspectRatio"));
+
+        let opts = SvgOptions {
+            preserve_aspect_ratio: Some("none".into()),
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        let open = &svg[..svg.find('>').unwrap()];
+        assert!(open.ends_with(r#"" preserveAspectRatio="none""#));
+        for tile in render_tiles(&r, None, &opts, 1, 2) {
+            assert!(tile.contains(r#"preserveAspectRatio="none">"#));
+        }
+    }
+
+    #[test]
+    fn test_interstrand_pair_color() {
+        let opts = SvgOptions {
+            interstrand_pair_color: Some("orange".into()),
+            pair_style: PairStyle::LineWithDot,
+            ..SvgOptions::default()
+        };
+        // Pairs (0, 9) and (1, 8) cross the nick; (3, 6) stays on strand 2
+        let svg = crate::draw_svg("((.+(..).))", None, &opts);
+        assert_eq!(svg.matches(r#"stroke="orange""#).count(), 2);
+        assert_eq!(svg.matches(r#"fill="orange""#).count(), 2);
+        assert_eq!(
+            svg.matches(r#"stroke-linecap="round" stroke-width="2.5" stroke="black""#)
+                .count(),
+            1
+        );
+
+        let mono = SvgOptions {
+            monochrome: true,
+            ..opts
+        };
+        assert!(!crate::draw_svg("((.+(..).))", None, &mono).contains("orange"));
+    }
+
+    #[test]
+    fn test_render_to() {
+        let r = crate::layout("((..((...))..))").unwrap();
+        let opts = SvgOptions {
+            sequence_track: true,
+            legend: Legend::Position,
+            ..SvgOptions::default()
+        };
+        let mut out = String::new();
+        render_to(&mut out, &r, Some("GGAAGGAAACCAACC"), &opts).unwrap();
+        assert_eq!(out, render(&r, Some("GGAAGGAAACCAACC"), &opts));
+
+        // Writer errors are reported, and nothing more is written after them
+        struct Limited {
+            left: usize,
+            calls: usize,
+        }
+        impl Write for Limited {
+            fn write_str(&mut self, _: &str) -> std::fmt::Result {
+                self.calls += 1;
+                self.left = self.left.checked_sub(1).ok_or(std::fmt::Error)?;
+                Ok(())
+            }
+        }
+        let mut w = Limited { left: 3, calls: 0 };
+        assert!(render_to(&mut w, &r, None, &opts).is_err());
+        assert_eq!(w.calls, 4);
+    }
+
+    #[test]
+    fn test_render_panel() {
+        let a = crate::layout("((...))").unwrap();
+        let b = crate::layout("((((....))))").unwrap();
+        let items = [
+            (a.clone(), Some("GGAAACC".to_string())),
+            (b.clone(), None),
+            (a, None),
+        ];
+        let opts = SvgOptions::default();
+        let svg = render_panel(&items, &opts, 2);
+
+        // Uniform cells sized to the larger structure, two columns by two rows
+        let fa = compute_frame(&items[0].0, &opts);
+        let fb = compute_frame(&b, &opts);
+        let (cell_w, cell_h) = (fa.struct_w.max(fb.struct_w), fa.vb_h.max(fb.vb_h));
+        let row_h = opts.font_size + 8.0 + cell_h;
+        assert!(svg.starts_with(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0.00 0.00 {:.2} {:.2}">"#,
+            2.0 * cell_w,
+            2.0 * row_h
+        )));
+        assert_eq!(svg.matches("<g transform=\"translate(").count(), 3);
+        assert_eq!(svg.matches("<circle").count(), 7 + 12 + 7);
+        for label in ["1", "2", "3"] {
+            assert!(svg.contains(&format!(">{label}</text>")));
+        }
+        // Each cell carries the same markup as a standalone render
+        let (body, _) = render_body(&b, None, &opts);
+        assert!(svg.contains(&body));
+
+        assert!(render_panel(&items, &opts, 0).is_empty());
+        assert!(render_panel(&[], &opts, 2).is_empty());
+        // A legend is drawn once, to the right of the grid
+        let nucleotide = SvgOptions {
+            legend: Legend::Nucleotide,
+            ..SvgOptions::default()
+        };
+        let svg = render_panel(&items, &nucleotide, 3);
+        assert_eq!(svg.matches(">A</text>").count(), 1);
+    }
+
+    #[test]
+    fn test_group_layers() {
+        let r = crate::layout("((...))").unwrap();
+        let opts = SvgOptions {
+            show_labels: true,
+            legend: Legend::Nucleotide,
+            ..SvgOptions::default()
+        };
+        let flat = render(&r, Some("GGAAACC"), &opts);
+        assert!(!flat.contains("<g"));
+
+        let grouped = render(
+            &r,
+            Some("GGAAACC"),
+            &SvgOptions {
+                group_layers: true,
+                ..opts
+            },
+        );
+        let ids = ["bonds", "backbone", "arrows", "bases", "labels", "legend"];
+        let starts: Vec<usize> = ids
+            .iter()
+            .map(|id| grouped.find(&format!(r#"<g id="{id}">"#)).unwrap())
+            .collect();
+        assert!(starts.is_sorted());
+        assert_eq!(grouped.matches("<g ").count(), ids.len());
+        assert_eq!(grouped.matches("</g>").count(), ids.len());
+        // Same elements in the same order once the wrappers are removed
+        let mut stripped = grouped.replace("</g>", "");
+        for id in ids {
+            stripped = stripped.replace(&format!(r#"<g id="{id}">"#), "");
+        }
+        assert_eq!(stripped, flat);
+    }
+
+    #[test]
+    fn test_legend_title() {
+        let prob = SvgOptions {
+            probabilities: Some(vec![0.5; 3]),
+            ..SvgOptions::default()
+        };
+        assert!(render_legend_svg(&prob, 200.0).contains(">Equilibrium probability</text>"));
+
+        let shape = SvgOptions {
+            legend_title: Some("SHAPE <reactivity>".into()),
+            ..prob
+        };
+        let svg = render_legend_svg(&shape, 200.0);
+        assert!(svg.contains(">SHAPE &lt;reactivity&gt;</text>"));
+        assert!(!svg.contains("Equilibrium probability"));
+
+        let nucleotide = SvgOptions {
+            legend: Legend::Nucleotide,
+            legend_title: Some("Base".into()),
+            ..SvgOptions::default()
+        };
+        assert!(render_legend_svg(&nucleotide, 200.0).contains(">Base</text>"));
+    }
+
+    #[test]
+    fn test_render_legend_svg() {
+        let opts = SvgOptions {
+            legend: Legend::Nucleotide,
+            ..SvgOptions::default()
+        };
+        let svg = render_legend_svg(&opts, 200.0);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"viewBox="0.00 0.00 80.00 200.00""#));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert!(svg.ends_with("</svg>"));
+
+        // Same markup as the legend of a full render at the same height
+        let r = crate::layout("((...))").unwrap();
+        let full = render(&r, None, &opts);
+        let frame = compute_frame(&r, &opts);
+        let mut legend = String::new();
+        render_legend(&mut legend, 0.0, 0.0, frame.vb_h, &opts);
+        let standalone = render_legend_svg(&opts, frame.vb_h);
+        assert!(standalone.contains(&legend));
+        assert_eq!(full.matches("<circle").count(), 7 + 4);
+
+        let prob = SvgOptions {
+            probabilities: Some(vec![0.5; 3]),
+            ..SvgOptions::default()
+        };
+        let svg = render_legend_svg(&prob, 200.0);
+        assert!(svg.contains("prob-grad"));
+
+        let mono = SvgOptions {
+            monochrome: true,
+            ..opts.clone()
+        };
+        assert!(render_legend_svg(&mono, 200.0).contains("<pattern"));
+        assert!(!render_legend_svg(&SvgOptions::default(), 200.0).contains("<circle"));
+    }
+
+    #[test]
+    fn test_coord_precision() {
+        let r = crate::layout("((...))").unwrap();
+        let circle_cx = |prec| {
+            let opts = SvgOptions {
+                coord_precision: prec,
+                ..SvgOptions::default()
+            };
+            let svg = render(&r, None, &opts);
+            let at = svg.find("<circle").unwrap();
+            let cx = &svg[at..][svg[at..].find("cx=\"").unwrap() + 4..];
+            cx[..cx.find('"').unwrap()].to_string()
+        };
+        assert_eq!(circle_cx(2), format!("{:.2}", r.layout.bases[0].x * 50.0));
+        assert_eq!(circle_cx(4), format!("{:.4}", r.layout.bases[0].x * 50.0));
+        assert!(!circle_cx(0).contains('.'));
+    }
+
+    #[test]
+    fn test_min_arc_sweep() {
+        let r = crate::layout("((.....))").unwrap();
+        let arcs = |svg: &str| svg.matches(r#"fill="none" stroke-linejoin"#).count();
+        let plain = render(&r, None, &SvgOptions::default());
+        assert!(arcs(&plain) > 0);
+
+        let opts = SvgOptions {
+            min_arc_sweep: PI,
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        assert_eq!(arcs(&svg), 0);
+        assert_eq!(
+            svg.matches("<line").count(),
+            plain.matches("<line").count() + arcs(&plain)
+        );
+    }
+
+    #[test]
+    fn test_debug_grid() {
+        let r = crate::layout("((...))").unwrap();
+        assert!(!render(&r, None, &SvgOptions::default()).contains("#dddddd"));
+
+        let opts = SvgOptions {
+            debug_grid: Some(1.0),
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        // Behind everything else
+        let grid = svg.find("#dddddd").unwrap();
+        assert!(grid < svg.find("<line").unwrap());
+        let (_, frame) = render_body(&r, None, &opts);
+        let path = &svg[svg.find(r#"<path d="M"#).unwrap()..grid];
+        let count = |c| path.matches(c).count() as f64;
+        // One line per 50 px (1 unit at scale 50) in each direction
+        assert!((count('V') - (frame.struct_w / 50.0).round()).abs() <= 1.0);
+        assert!((count('H') - (frame.vb_h / 50.0).round()).abs() <= 1.0);
+        assert!(svg.contains(r#"M0 "#));
+    }
+
+    #[test]
+    fn test_square() {
+        let r = crate::layout("((((((....))))))").unwrap();
+        let opts = SvgOptions {
+            square: true,
+            legend: Legend::Position,
+            ..SvgOptions::default()
+        };
+        let (_, plain) = render_body(&r, None, &SvgOptions::default());
+        let (_, frame) = render_body(&r, None, &opts);
+        assert!(plain.vb_h > plain.struct_w);
+        assert_eq!(frame.struct_w, frame.vb_h);
+        assert_eq!(frame.vb_h, plain.vb_h);
+        // Centered on the same structure
+        assert!(
+            ((frame.vb_x + frame.struct_w / 2.0) - (plain.vb_x + plain.struct_w / 2.0)).abs()
+                < 1e-9
+        );
+        // Legend is reserved beside the square
+        let svg = render(&r, None, &opts);
+        assert!(svg.contains(&format!(
+            r#"viewBox="{:.2} {:.2} {:.2} {:.2}""#,
+            frame.vb_x,
+            frame.vb_y,
+            frame.struct_w + frame.legend_w,
+            frame.vb_h
+        )));
+    }
+
+    #[test]
+    fn test_padding_sides() {
+        let r = crate::layout("((...))").unwrap();
+        let view_box = |opts: &SvgOptions| -> Vec<f64> {
+            let svg = render(&r, None, opts);
+            let vb = svg.split(r#"viewBox=""#).nth(1).unwrap();
+            let vb = &vb[..vb.find('"').unwrap()];
+            vb.split(' ').map(|v| v.parse().unwrap()).collect()
+        };
+        let base = view_box(&SvgOptions {
+            padding: 0.0,
+            ..SvgOptions::default()
+        });
+        let padded = view_box(&SvgOptions {
+            padding_sides: Some([1.0, 2.0, 3.0, 4.0]),
+            ..SvgOptions::default()
+        });
+        let close = |a: f64, b: f64| (a - b).abs() < 0.011;
+        assert!(close(padded[0], base[0] - 4.0));
+        assert!(close(padded[1], base[1] - 1.0));
+        assert!(close(padded[2], base[2] + 6.0));
+        assert!(close(padded[3], base[3] + 4.0));
+    }
+
+    #[test]
+    fn test_probability_scale() {
+        let unit = SvgOptions {
+            probabilities: Some(vec![0.0, 0.85, 0.3, 1.0]),
+            ..SvgOptions::default()
+        };
+        let percent = SvgOptions {
+            probabilities: Some(vec![0.0, 85.0, 30.0, 100.0]),
+            probability_scale: ProbScale::Percent,
+            ..SvgOptions::default()
+        };
+        let a = crate::draw_svg("(..)", None, &unit);
+        let b = crate::draw_svg("(..)", None, &percent);
+        assert!(b.contains(&format!(r#"fill="{}""#, probability_to_color(0.85))));
+        // Same colors, different legend ticks
+        assert!(a.contains(">0.5</text>") && !a.contains(">50</text>"));
+        assert!(b.contains(">50</text>") && !b.contains(">0.5</text>"));
+        let fills = |svg: &str| -> Vec<String> {
+            svg.split("<circle")
+                .skip(1)
+                .map(|c| {
+                    c[c.find("fill=").unwrap()..]
+                        .split('"')
+                        .nth(1)
+                        .unwrap()
+                        .to_string()
+                })
+                .collect()
+        };
+        assert_eq!(fills(&a), fills(&b));
+    }
+
+    #[test]
+    fn test_diverging() {
+        let opts = SvgOptions {
+            probabilities: Some(vec![-2.0, 0.0, 0.5, 1.0]),
+            diverging: true,
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("(..)", None, &opts);
+        // Zero is neutral, each sign scaled by its own bound
+        assert!(svg.contains(&format!(r#"fill="{}""#, Colormap::Coolwarm.color(0.5))));
+        assert!(svg.contains(&format!(r#"fill="{}""#, Colormap::Coolwarm.color(0.0))));
+        assert!(svg.contains(&format!(r#"fill="{}""#, Colormap::Coolwarm.color(0.75))));
+        assert!(svg.contains(&format!(r#"fill="{}""#, Colormap::Coolwarm.color(1.0))));
+        for tick in ["-2", "-1", "0", "0.5", "1"] {
+            assert!(
+                svg.contains(&format!(">{tick}</text>")),
+                "missing tick {tick}"
+            );
+        }
+        assert!(svg.contains(">Value</text>"));
+
+        let ranged = SvgOptions {
+            value_range: Some((-4.0, 4.0)),
+            ..opts
+        };
+        let svg = crate::draw_svg("(..)", None, &ranged);
+        assert!(svg.contains(&format!(r#"fill="{}""#, Colormap::Coolwarm.color(0.25))));
+        assert!(svg.contains(">-4</text>") && svg.contains(">4</text>"));
+    }
+
+    #[test]
+    fn test_pair_style() {
+        let render = |pair_style| {
+            let opts = SvgOptions {
+                pair_style,
+                show_arrows: false,
+                ..SvgOptions::default()
+            };
+            crate::draw_svg("((..))", None, &opts)
+        };
+        let dot = r#"<circle r="5" "#;
+        let line = r#"stroke-linecap="round" stroke-width="2.5""#;
+
+        let svg = render(PairStyle::Line);
+        assert_eq!(svg.matches(line).count(), 2);
+        assert!(!svg.contains(dot));
+
+        let svg = render(PairStyle::MidpointDot);
+        assert_eq!(svg.matches(line).count(), 0);
+        assert_eq!(svg.matches(dot).count(), 2);
+
+        let svg = render(PairStyle::LineWithDot);
+        assert_eq!(svg.matches(line).count(), 2);
+        assert_eq!(svg.matches(dot).count(), 2);
+
+        // Two stubs per pair, each pair_tick_len long on a 50 px bond
+        let svg = render(PairStyle::Tick);
+        assert_eq!(svg.matches(line).count(), 4);
+        assert!(!svg.contains(dot));
+        let r = crate::layout("((..))").unwrap();
+        let (b0, b5) = (&r.layout.bases[0], &r.layout.bases[5]);
+        assert!(((b0.x - b5.x).hypot(b0.y - b5.y) - 1.0).abs() < 1e-9);
+        let strands = r.strands();
+        // Stubs longer than half the bond are clamped so they meet in the middle
+        for (pair_tick_len, expected) in [(15.0, 15.0), (40.0, 25.0)] {
+            let opts = SvgOptions {
+                pair_style: PairStyle::Tick,
+                pair_tick_len,
+                ..SvgOptions::default()
+            };
+            let mut ticks = String::new();
+            render_pair_bonds(
+                &mut ticks,
+                &r.layout.bases,
+                &r.pairs,
+                &strands,
+                opts.scale,
+                &opts,
+            );
+            assert_eq!(ticks.matches("<line").count(), 4);
+            for seg in ticks.split("<line").skip(1) {
+                let num = |k: &str| -> f64 {
+                    let v = &seg[seg.find(k).unwrap() + k.len()..];
+                    v[..v.find('"').unwrap()].parse().unwrap()
+                };
+                let len = (num(r#"x2=""#) - num(r#"x1=""#)).hypot(num(r#"y2=""#) - num(r#"y1=""#));
+                assert!((len - expected).abs() < 0.02, "{pair_tick_len}: {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_monochrome() {
+        let opts = SvgOptions {
+            monochrome: true,
+            backbone_color: "red".into(),
+            pair_color: "blue".into(),
+            per_base_colors: Some(vec!["green".into(); 6]),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((..))", None, &opts);
+        assert!(!svg.contains("red") && !svg.contains("blue") && !svg.contains("green"));
+        assert!(svg.contains(r#"fill="white" stroke-width="2.5" stroke="black""#));
+
+        // Nucleotide coloring becomes hatch patterns, in markers and legend
+        let opts = SvgOptions {
+            monochrome: true,
+            base_colors: Some(DEFAULT_NUCLEOTIDE_COLORS.map(String::from)),
+            legend: Legend::Nucleotide,
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((..))", Some("GACUGC"), &opts);
+        assert!(svg.contains(r#"<pattern id="mono-a""#));
+        assert_eq!(svg.matches(r#"fill="url(#mono-g)""#).count(), 3);
+        assert!(!svg.contains(r#"fill="green""#));
+
+        // Probabilities map through grayscale
+        let opts = SvgOptions {
+            monochrome: true,
+            probabilities: Some(vec![1.0; 6]),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((..))", None, &opts);
+        assert!(svg.contains(r##"fill="#000000""##));
+    }
+
+    #[test]
+    fn test_strand_colors() {
+        let opts = SvgOptions {
+            strand_colors: Some(vec!["red".into()]),
+            ..SvgOptions::default()
+        };
+        let svg = crate::draw_svg("((..+..))", None, &opts);
+        // First strand uses its color, second falls back to backbone_color
+        assert!(svg.contains(r#"stroke-width="5" stroke="red""#));
+        assert!(svg.contains(r#"stroke-opacity="1" stroke-width="5" stroke="black""#));
+    }
+
+    #[test]
+    fn test_default_nucleotide_preset() {
+        assert_eq!(DEFAULT_NUCLEOTIDE_COLORS, ["green", "red", "black", "blue"]);
+    }
+
+    #[test]
+    fn test_per_base_opacity() {
+        let r = crate::layout("((...))").unwrap();
+        let opts = SvgOptions {
+            per_base_opacity: Some(vec![0.25, 1.5, -1.0]),
+            probabilities: Some(vec![0.5; 7]),
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, None, &opts);
+        let circles: Vec<&str> = svg.split("<circle").skip(1).collect();
+        assert!(circles[0].contains(r#"fill-opacity="0.25" stroke-opacity="0.25""#));
+        assert!(circles[1].contains(r#"fill-opacity="1""#));
+        assert!(circles[2].contains(r#"fill-opacity="0""#));
+        // Missing entries stay opaque
+        assert!(!circles[3].contains("opacity"));
+        // Composes with probability coloring
+        let hue = probability_to_color(0.5);
+        assert!(circles[0].contains(&format!(r#"fill="{hue}""#)));
+    }
+
+    #[test]
+    fn test_nucleotide_palette() {
+        let r = crate::layout("((...))").unwrap();
+        let opts = SvgOptions {
+            nucleotide_palette: Some(NucleotidePalette::ColorblindSafe),
+            legend: Legend::Nucleotide,
+            ..SvgOptions::default()
+        };
+        let svg = render(&r, Some("GAAAUCC"), &opts);
+        // Markers (G, A, U, C) and the legend both use the palette
+        for color in CB_SAFE_NUCLEOTIDE_COLORS {
+            assert!(svg.contains(&format!(r#"fill="{color}""#)), "{color}");
+        }
+        assert!(!svg.contains(r#"fill="green""#));
+        assert!(render_legend_svg(&opts, 200.0).contains(CB_SAFE_NUCLEOTIDE_COLORS[0]));
+
+        // Explicit base_colors win
+        let explicit = SvgOptions {
+            base_colors: Some(DEFAULT_NUCLEOTIDE_COLORS.map(String::from)),
+            ..opts.clone()
+        };
+        let svg = render(&r, Some("GAAAUCC"), &explicit);
+        assert!(!svg.contains(CB_SAFE_NUCLEOTIDE_COLORS[0]));
+
+        assert_eq!(
+            NucleotidePalette::default().colors(),
+            DEFAULT_NUCLEOTIDE_COLORS
+        );
     }
 }
@@ -1,6 +1,220 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+/// Options controlling loop geometry.
+///
+/// Defaults reproduce the reference layout exactly.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LayoutOptions {
+    /// Extra arc weight per unpaired base, relative to the default spacing (default: 1.0).
+    /// Values above 1.0 enlarge loops that contain unpaired bases so those bases
+    /// spread out along the arc. Loops without unpaired bases (stems) are unaffected.
+    pub loop_spacing: f64,
+    /// Lay out bulges so the two flanking helices stay collinear, with the unpaired
+    /// bases pushed to one side (default: false)
+    pub straight_bulges: bool,
+    /// How backbone segments are represented (default: Auto)
+    pub segment_mode: SegmentMode,
+    /// Draw structures without any pairs as a straight line of bases instead of
+    /// returning no result (default: false). Multi-strand structures without pairs
+    /// are always drawn, one row per strand.
+    pub draw_unstructured: bool,
+    /// Lay out the unpaired 5′ and 3′ tails (before the first and after the last
+    /// pair) on straight lines leading away from the terminal pairs instead of along
+    /// the external loop circle (default: false)
+    pub straight_tails: bool,
+    /// Helices to draw coaxially stacked, each entry naming one base of two pairs
+    /// that close the same loop (default: empty). The loop's unpaired spacing is
+    /// redistributed so the two helices leave it in opposite directions, i.e.
+    /// collinear. Entries that don't fit (pairs not on a common loop, or too many
+    /// pairs on one side) are ignored; later entries win on conflicts.
+    pub coaxial: Vec<(usize, usize)>,
+    /// Upper bound on the radius of loops whose size is solved from their contents
+    /// (multiloops, hairpins and interior loops; default: None). A capped loop keeps
+    /// its pair bonds at unit length and shortens the unpaired steps instead, so
+    /// bases crowd together on highly-branched loops rather than the figure growing.
+    /// The radius never drops below what the pair bonds alone need to close the circle.
+    pub max_loop_radius: Option<f64>,
+    /// Smallest radius of loops whose size is solved from their contents (default:
+    /// 0.0, only the pair bond width as a floor). Small multiloops without unpaired
+    /// bases otherwise close as tightly as their pair bonds allow; a larger floor
+    /// leaves the extra arc as even gaps between the pairs. `max_loop_radius` wins
+    /// when the two conflict.
+    pub min_radius: f64,
+    /// Smallest distance between consecutive bases around loops whose size is solved
+    /// from their contents (default: None). Such loops grow until every step clears
+    /// it, e.g. `2 * base_radius / scale` keeps base markers from touching. Takes
+    /// precedence over `max_loop_radius`.
+    pub min_base_spacing: Option<f64>,
+    /// Aspect ratio of hairpins and internal multiloops (default: 1.0, circles). Each
+    /// such loop becomes an ellipse whose extent along its parent helix is this times
+    /// its width across it, so values below 1.0 pull branches and hairpin tips in
+    /// towards the parent helix. Pair bonds keep unit length and base steps keep their
+    /// chord length; the ellipse grows to fit them. Loops too small to flatten
+    /// without bases across them coming closer than half a bond stay circles, as do
+    /// interior loops and bulges. `pair_angle` and `arc_angle` of such loops still
+    /// describe the circle.
+    pub elliptical_loops: f64,
+    /// Direction (radians) of the first external pair from the external loop
+    /// center, which orients the whole layout (default: π/2, first stem pointing up)
+    pub root_angle: f64,
+    /// Include structural feature counts as `stats` in the result (default: false)
+    pub stats: bool,
+    /// Skew of the spacing along each run of unpaired bases between two pairs
+    /// (default: 0.0, even spacing). Positive values pack the bases towards the
+    /// flanking helices and leave the extra room mid-run; negative values do the
+    /// opposite. The run's total arc is unchanged. Clamped to [-0.9, 0.9].
+    pub unpaired_bias: f64,
+    /// Longest helix, in pairs, drawn at full length (default: None, no limit).
+    /// Longer helices keep their first and last few pairs at the usual spacing
+    /// (half of this count each, the odd one at the outer end) and squeeze the
+    /// pairs between them into the length of a single step, for schematic
+    /// overviews. Values below 2 count as 2.
+    pub stem_compaction: Option<usize>,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            loop_spacing: 1.0,
+            straight_bulges: false,
+            segment_mode: SegmentMode::Auto,
+            draw_unstructured: false,
+            straight_tails: false,
+            coaxial: Vec::new(),
+            max_loop_radius: None,
+            min_radius: 0.0,
+            min_base_spacing: None,
+            elliptical_loops: 1.0,
+            root_angle: std::f64::consts::FRAC_PI_2,
+            stats: false,
+            unpaired_bias: 0.0,
+            stem_compaction: None,
+        }
+    }
+}
+
+/// Backbone segment representation.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentMode {
+    /// Arcs on loops with unpaired bases or 3+ pairs, lines elsewhere (stems,
+    /// empty hairpins)
+    #[default]
+    Auto,
+    /// Arcs on every loop circle, including 2-pair stems
+    AllArcs,
+    /// Straight lines between bases everywhere
+    AllLines,
+}
+
+/// Options controlling dot-bracket parsing.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ParseOptions {
+    /// Extra characters treated like `.` (unpaired), e.g. `:`, `,`, `_` used as
+    /// modified-base annotations (default: none)
+    pub unpaired_chars: Vec<char>,
+}
+
+/// Why dot-bracket input was rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// Character that is not a bracket, `.`, `+`, `&`, ASCII whitespace, or a configured
+    /// unpaired character
+    BadCharacter(char),
+    /// Bracket without a partner
+    Unmatched(char),
+    /// `(`/`)` and `<`/`>` pairs cross, e.g. `(<)>`
+    Crossing(char, char),
+    /// Leading, trailing, or consecutive strand breaks (`+` or `&`)
+    EmptyStrand,
+    /// Vienna input without a structure line
+    MissingStructure,
+    /// Vienna sequence length differs from the number of structure bases
+    LengthMismatch { sequence: usize, structure: usize },
+    /// Partner array entry for this base is neither -1 nor another base index
+    BadPartner(usize),
+    /// Base i lists j as partner but j does not list i
+    Asymmetric(usize, usize),
+    /// Strand break position out of range or not increasing
+    BadNick(usize),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadCharacter(c) => write!(f, "bad dot-parens character {c:?}"),
+            Self::Unmatched(c) => write!(f, "unmatched {c} bracket"),
+            Self::Crossing(open, close) => write!(f, "crossing {open}{close} pair"),
+            Self::EmptyStrand => write!(f, "empty strand"),
+            Self::MissingStructure => write!(f, "missing structure line"),
+            Self::LengthMismatch {
+                sequence,
+                structure,
+            } => write!(
+                f,
+                "sequence length {sequence} does not match structure length {structure}"
+            ),
+            Self::BadPartner(i) => write!(f, "base {i} has an invalid partner"),
+            Self::Asymmetric(i, j) => write!(f, "base {i} pairs with {j} but not vice versa"),
+            Self::BadNick(k) => write!(f, "invalid strand break at {k}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Why no layout could be produced.
+///
+/// Covers the failure modes of `try_draw_svg`: `Parse` (malformed notation, a.k.a.
+/// parse failed), `Empty` (empty structure), `NoPairs` (no loops to decompose) and
+/// `SequenceLength` (sequence mismatch), plus `Pseudoknot`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawError {
+    /// The input notation was rejected
+    Parse(ParseError),
+    /// Crossing pairs, which cannot be laid out as nested loops
+    Pseudoknot,
+    /// No bases
+    Empty,
+    /// No pairs in a single strand, and `LayoutOptions::draw_unstructured` is off
+    NoPairs,
+    /// Sequence length (strand breaks excluded) differs from the number of bases
+    SequenceLength { sequence: usize, bases: usize },
+}
+
+impl std::fmt::Display for DrawError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "parse error: {e}"),
+            Self::Pseudoknot => write!(f, "pseudoknotted structures are not supported"),
+            Self::Empty => write!(f, "structure has no bases"),
+            Self::NoPairs => write!(f, "structure has no base pairs"),
+            Self::SequenceLength { sequence, bases } => {
+                write!(f, "sequence length {sequence} != {bases} bases")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DrawError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for DrawError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
 
 /// Pair table from parsing dot-bracket-plus notation
+#[derive(Clone, Debug)]
 pub struct PairTable {
     /// pairs[i] = j means base i is paired with base j; pairs[i] = i means unpaired
     pub pairs: Vec<usize>,
@@ -18,11 +232,36 @@ pub struct LoopPair {
     pub neighbor: usize,
 }
 
+/// Structural classification of a loop
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoopKind {
+    External,
+    /// Closing pair only, with unpaired bases
+    Hairpin,
+    /// Closing pair and one child pair, no unpaired bases (helix step)
+    Stack,
+    /// One child pair, unpaired bases on one side only
+    Bulge,
+    /// One child pair, unpaired bases on both sides
+    Interior,
+    /// Two or more child pairs
+    Multi,
+}
+
 /// Loop geometry — fields in alphabetical order for JSON serialization
 #[derive(Serialize, Clone, Debug)]
 pub struct Loop {
     pub arc_angle: f64,
+    /// Set for loops drawn as ellipses (see `LayoutOptions::elliptical_loops`), with
+    /// `radius` the semi-axis along `Ellipse::rotation`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ellipse: Option<Ellipse>,
     pub height: f64,
+    /// Structural classification, kept out of the JSON so the default schema stays
+    /// stable (see `loop_kind`)
+    #[serde(skip)]
+    pub kind: LoopKind,
     pub pair_angle: f64,
     pub pairs: Vec<LoopPair>,
     pub radius: f64,
@@ -30,6 +269,49 @@ pub struct Loop {
     pub y: f64,
 }
 
+impl Loop {
+    /// Point on the loop outline at (parametric) angle `t`.
+    pub fn point(&self, t: f64) -> (f64, f64) {
+        match &self.ellipse {
+            None => (
+                self.x + self.radius * t.cos(),
+                self.y + self.radius * t.sin(),
+            ),
+            Some(e) => e.point(self.x, self.y, self.radius, t),
+        }
+    }
+}
+
+/// Shape of an elliptical loop or arc beyond its center and `r`/`radius`, the
+/// semi-axis pointing along `rotation`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Ellipse {
+    /// Direction (radians) of the first semi-axis
+    pub rotation: f64,
+    /// Semi-axis perpendicular to `rotation`
+    pub ry: f64,
+}
+
+impl Ellipse {
+    /// Point at parametric angle `t`, measured like a circle angle so that
+    /// `t = rotation` is the end of the first semi-axis.
+    pub fn point(&self, cx: f64, cy: f64, r: f64, t: f64) -> (f64, f64) {
+        let u = t - self.rotation;
+        let (lx, ly) = (r * u.cos(), self.ry * u.sin());
+        let (c, s) = (self.rotation.cos(), self.rotation.sin());
+        (cx + lx * c - ly * s, cy + lx * s + ly * c)
+    }
+
+    /// Unit normal pointing out of the ellipse at parametric angle `t`.
+    pub fn normal(&self, r: f64, t: f64) -> (f64, f64) {
+        let u = t - self.rotation;
+        let (nx, ny) = (self.ry * u.cos(), r * u.sin());
+        let len = nx.hypot(ny);
+        let (c, s) = (self.rotation.cos(), self.rotation.sin());
+        ((nx * c - ny * s) / len, (nx * s + ny * c) / len)
+    }
+}
+
 /// Base coordinates — fields in alphabetical order
 #[derive(Serialize, Clone, Debug)]
 pub struct Base {
@@ -64,6 +346,9 @@ pub struct LineSegment {
 /// Arc segment
 #[derive(Serialize, Clone, Debug)]
 pub struct ArcSegment {
+    /// Set for arcs on elliptical loops
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ellipse: Option<Ellipse>,
     pub r: f64,
     pub t1: f64,
     pub t2: f64,
@@ -71,6 +356,42 @@ pub struct ArcSegment {
     pub y: f64,
 }
 
+impl ArcSegment {
+    /// Point on the arc's circle or ellipse at angle `t`.
+    pub fn point(&self, t: f64) -> (f64, f64) {
+        match &self.ellipse {
+            None => (self.x + self.r * t.cos(), self.y + self.r * t.sin()),
+            Some(e) => e.point(self.x, self.y, self.r, t),
+        }
+    }
+
+    /// Length of the arc from `t2` to `t1` the short way round.
+    pub fn length(&self) -> f64 {
+        let delta = crate::svg::normalize_angle(self.t1 - self.t2);
+        let Some(e) = &self.ellipse else {
+            return self.r * delta.abs();
+        };
+        // Simpson's rule over the parametric speed
+        let steps = 64;
+        let h = delta / steps as f64;
+        let speed = |t: f64| {
+            let u = t - e.rotation;
+            (self.r * u.sin()).hypot(e.ry * u.cos())
+        };
+        let sum: f64 = (0..=steps)
+            .map(|k| {
+                let w = if k == 0 || k == steps {
+                    1.0
+                } else {
+                    (2 + 2 * (k % 2)) as f64
+                };
+                w * speed(self.t2 + k as f64 * h)
+            })
+            .sum();
+        (sum * h / 3.0).abs()
+    }
+}
+
 /// A segment is either a line or an arc (untagged for JSON)
 #[derive(Serialize, Clone, Debug)]
 #[serde(untagged)]
@@ -86,6 +407,249 @@ pub struct DrawResult {
     pub nicks: Vec<usize>,
     pub pairs: Vec<usize>,
     pub segments: Vec<Vec<Segment>>,
+    /// Structural feature counts, present when `LayoutOptions::stats` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<StructureStats>,
+}
+
+impl DrawResult {
+    /// Strand base ranges as (start, end), end exclusive, in sequence order.
+    pub fn strands(&self) -> Vec<(usize, usize)> {
+        strand_ranges(&self.nicks, self.pairs.len())
+    }
+
+    /// Index into `layout.loops` of the loop closed by pair (i, j).
+    ///
+    /// Order of `i` and `j` does not matter. Returns None if they are not paired.
+    pub fn loop_index_of_pair(&self, i: usize, j: usize) -> Option<usize> {
+        let (i, j) = if i < j { (i, j) } else { (j, i) };
+        if self.pairs.get(i) != Some(&j) || i == j {
+            return None;
+        }
+        // The enclosing loop lists (i, j) as a child pair whose neighbor is the closed loop
+        self.layout
+            .loops
+            .iter()
+            .flat_map(|l| &l.pairs)
+            .find(|p| p.first == i && p.last == j)
+            .map(|p| p.neighbor)
+    }
+
+    /// Loop that base `i` sits on as (loop index, center x, center y, radius).
+    ///
+    /// Uses `Base.loop1`; for a paired base that is the loop on the 5′ side of
+    /// its backbone, which may differ from `loop2`. Returns None if `i` is out of
+    /// range or the layout has no loops.
+    pub fn loop_of_base(&self, i: usize) -> Option<(usize, f64, f64, f64)> {
+        let li = self.layout.bases.get(i)?.loop1;
+        let l = self.layout.loops.get(li)?;
+        Some((li, l.x, l.y, l.radius))
+    }
+
+    /// Bases on each loop, indexed like `layout.loops`, in ascending order.
+    ///
+    /// A paired base sits on the loops on both sides of its pair (`loop1` and
+    /// `loop2`), so it is listed under both, e.g. for highlighting a loop's bases
+    /// when it is clicked.
+    pub fn loop_members(&self) -> Vec<Vec<usize>> {
+        let mut members = vec![Vec::new(); self.layout.loops.len()];
+        for (i, b) in self.layout.bases.iter().enumerate() {
+            for li in [b.loop1, b.loop2] {
+                if let Some(m) = members.get_mut(li) {
+                    if m.last() != Some(&i) {
+                        m.push(i);
+                    }
+                }
+            }
+        }
+        members
+    }
+
+    /// Rotate the whole drawing counterclockwise by `angle` radians about the
+    /// origin: coordinates, base and pair angles, and arc angles alike, so arcs
+    /// still run from `t1` to `t2` about their rotated centers.
+    pub fn rotate(&mut self, angle: f64) {
+        let cos_a = angle.cos();
+        let sin_a = angle.sin();
+
+        for b in &mut self.layout.bases {
+            let (x, y) = (b.x, b.y);
+            b.x = x * cos_a - y * sin_a;
+            b.y = x * sin_a + y * cos_a;
+            let (xt, yt) = (b.xt, b.yt);
+            b.xt = xt * cos_a - yt * sin_a;
+            b.yt = xt * sin_a + yt * cos_a;
+            b.angle1 += angle;
+            b.angle2 += angle;
+        }
+
+        for l in &mut self.layout.loops {
+            let (x, y) = (l.x, l.y);
+            l.x = x * cos_a - y * sin_a;
+            l.y = x * sin_a + y * cos_a;
+            if let Some(e) = &mut l.ellipse {
+                e.rotation += angle;
+            }
+            for p in &mut l.pairs {
+                p.angle += angle;
+            }
+        }
+
+        for segs in &mut self.segments {
+            for seg in segs {
+                match seg {
+                    Segment::Line(line) => {
+                        let (x, y) = (line.x, line.y);
+                        line.x = x * cos_a - y * sin_a;
+                        line.y = x * sin_a + y * cos_a;
+                        let (x1, y1) = (line.x1, line.y1);
+                        line.x1 = x1 * cos_a - y1 * sin_a;
+                        line.y1 = x1 * sin_a + y1 * cos_a;
+                    }
+                    Segment::Arc(arc) => {
+                        let (x, y) = (arc.x, arc.y);
+                        arc.x = x * cos_a - y * sin_a;
+                        arc.y = x * sin_a + y * cos_a;
+                        arc.t1 += angle;
+                        arc.t2 += angle;
+                        if let Some(e) = &mut arc.ellipse {
+                            e.rotation += angle;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shift the whole drawing by `(dx, dy)`. Arcs move their centers only;
+    /// `t1`/`t2` are unchanged.
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        for b in &mut self.layout.bases {
+            b.x += dx;
+            b.y += dy;
+            b.xt += dx;
+            b.yt += dy;
+        }
+
+        for l in &mut self.layout.loops {
+            l.x += dx;
+            l.y += dy;
+        }
+
+        for segs in &mut self.segments {
+            for seg in segs {
+                match seg {
+                    Segment::Line(line) => {
+                        line.x += dx;
+                        line.y += dy;
+                        line.x1 += dx;
+                        line.y1 += dy;
+                    }
+                    Segment::Arc(arc) => {
+                        arc.x += dx;
+                        arc.y += dy;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Stable hash of the drawn picture, for keying caches of rendered output.
+    ///
+    /// Covers pairs, nicks, and base and loop coordinates rounded to 1e-6, so float
+    /// noise does not change the value. Uses FNV-1a rather than `std`'s hasher,
+    /// whose output may differ across Rust versions.
+    pub fn layout_hash(&self) -> u64 {
+        let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |v: u64| {
+            for byte in v.to_le_bytes() {
+                h = (h ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        let rounded = |x: f64| (x * 1e6).round() as i64 as u64;
+        for &p in &self.pairs {
+            feed(p as u64);
+        }
+        for &k in &self.nicks {
+            feed(k as u64);
+        }
+        for b in &self.layout.bases {
+            feed(rounded(b.x));
+            feed(rounded(b.y));
+        }
+        for l in &self.layout.loops {
+            feed(rounded(l.x));
+            feed(rounded(l.y));
+            feed(rounded(l.radius));
+        }
+        h
+    }
+}
+
+/// Strand ranges (start, end exclusive) over `n` bases from nick positions.
+pub(crate) fn strand_ranges(nicks: &[usize], n: usize) -> Vec<(usize, usize)> {
+    let mut starts = nicks.to_vec();
+    starts.sort_unstable();
+    starts.dedup();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(si, &start)| (start, starts.get(si + 1).copied().unwrap_or(n)))
+        .filter(|&(start, end)| start < end)
+        .collect()
+}
+
+/// Structural feature counts — fields in alphabetical order
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StructureStats {
+    pub base_pairs: usize,
+    pub bulges: usize,
+    pub hairpins: usize,
+    pub interior_loops: usize,
+    /// Pairs in the longest helix of consecutively stacked pairs
+    pub longest_stem: usize,
+    pub multiloops: usize,
+    /// Helices of consecutively stacked pairs, a lone pair counting as one
+    pub stems: usize,
+}
+
+/// Base pair counts by nucleotide composition, see `pair_composition`
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PairComposition {
+    /// G–C pairs
+    pub gc: usize,
+    /// A–U pairs (T counts as U)
+    pub au: usize,
+    /// G–U wobble pairs (T counts as U)
+    pub gu: usize,
+    /// Pairs of two standard nucleotides that are neither Watson–Crick nor wobble
+    pub mismatch: usize,
+    /// Pairs involving any other character (e.g. IUPAC `N`)
+    pub ambiguous: usize,
+}
+
+/// Geometry summary of one loop circle, see `geometry::loop_metrics`
+#[derive(Serialize, Clone, Debug)]
+pub struct LoopMetrics {
+    pub radius: f64,
+    /// Angle subtended by one unpaired step
+    pub arc_angle: f64,
+    /// Angle subtended by one pair bond
+    pub pair_angle: f64,
+    pub circumference: f64,
+    /// Smallest distance between neighboring bases on the loop
+    pub min_base_spacing: f64,
+}
+
+/// Flat coordinate arrays for plotting tools (e.g. matplotlib scatter + lines)
+#[derive(Serialize, Clone, Debug)]
+pub struct Coords {
+    pub xs: Vec<f64>,
+    pub ys: Vec<f64>,
+    /// Base pairs as (i, j) with i < j
+    pub pairs: Vec<(usize, usize)>,
+    /// Strand base ranges as (start, end), end exclusive
+    pub strands: Vec<(usize, usize)>,
 }
 
 /// Internal loop info used during decomposition (not serialized)
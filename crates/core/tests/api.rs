@@ -0,0 +1,533 @@
+#[test]
+fn test_coords_hairpin() {
+    let c = rnadraw_core::coords("(((...)))").unwrap();
+    assert_eq!(c.xs.len(), 9);
+    assert_eq!(c.ys.len(), 9);
+    assert_eq!(c.pairs, vec![(0, 8), (1, 7), (2, 6)]);
+    assert_eq!(c.strands, vec![(0, 9)]);
+}
+
+#[test]
+fn test_coords_multi_strand() {
+    let c = rnadraw_core::coords("((.+.))").unwrap();
+    assert_eq!(c.xs.len(), 6);
+    assert_eq!(c.pairs, vec![(0, 5), (1, 4)]);
+    assert_eq!(c.strands, vec![(0, 3), (3, 6)]);
+}
+
+#[test]
+fn test_coords_invalid() {
+    assert!(rnadraw_core::coords("((").is_none());
+}
+
+#[test]
+fn test_loop_index_of_pair_external_stems() {
+    let r = rnadraw_core::layout("((..))((..))((..))").unwrap();
+    assert_eq!(r.loop_index_of_pair(0, 5), Some(1));
+    assert_eq!(r.loop_index_of_pair(1, 4), Some(2));
+    assert_eq!(r.loop_index_of_pair(6, 11), Some(3));
+    assert_eq!(r.loop_index_of_pair(12, 17), Some(4));
+    assert_eq!(r.loop_index_of_pair(13, 16), Some(5));
+    assert_eq!(r.loop_index_of_pair(7, 10), Some(6));
+    // Order of arguments does not matter
+    assert_eq!(r.loop_index_of_pair(17, 12), Some(4));
+    // Not a pair
+    assert_eq!(r.loop_index_of_pair(0, 1), None);
+    assert_eq!(r.loop_index_of_pair(2, 2), None);
+}
+
+#[test]
+fn test_loop_index_of_pair_nested_external_stems() {
+    // First external stem is processed depth-first; remaining stems are
+    // assigned in sequence order, then their subtrees in reverse.
+    let r = rnadraw_core::layout("((..))(((..)))((..))").unwrap();
+    let expected = [
+        ((0, 5), 1),
+        ((1, 4), 2),
+        ((6, 13), 3),
+        ((14, 19), 4),
+        ((15, 18), 5),
+        ((7, 12), 6),
+        ((8, 11), 7),
+    ];
+    for ((i, j), li) in expected {
+        assert_eq!(r.loop_index_of_pair(i, j), Some(li), "pair ({i}, {j})");
+    }
+    assert_eq!(r.layout.loops.len(), 8);
+}
+
+#[test]
+fn test_loop_of_base() {
+    let r = rnadraw_core::layout("((...))").unwrap();
+    let hairpin = r.loop_index_of_pair(1, 5).unwrap();
+    let (li, x, y, radius) = r.loop_of_base(3).unwrap();
+    assert_eq!(li, hairpin);
+    let l = &r.layout.loops[hairpin];
+    assert_eq!((x, y, radius), (l.x, l.y, l.radius));
+    let b = &r.layout.bases[3];
+    assert!(((b.x - x).hypot(b.y - y) - radius).abs() < 1e-9);
+
+    for i in 0..7 {
+        assert_eq!(r.loop_of_base(i).unwrap().0, r.layout.bases[i].loop1);
+    }
+    assert!(r.loop_of_base(7).is_none());
+}
+
+#[test]
+fn test_loop_members() {
+    let r = rnadraw_core::layout("((..((...))..))").unwrap();
+    let members = r.loop_members();
+    assert_eq!(members.len(), r.layout.loops.len());
+    let of = |i, j| &members[r.loop_index_of_pair(i, j).unwrap()];
+    assert_eq!(of(1, 13), &vec![1, 2, 3, 4, 10, 11, 12, 13]);
+    assert_eq!(of(5, 9), &vec![5, 6, 7, 8, 9]);
+    assert_eq!(of(0, 14), &vec![0, 1, 13, 14]);
+    assert_eq!(members[0], vec![0, 14]);
+}
+
+#[test]
+fn test_layout_hash() {
+    let a = rnadraw_core::layout("((...))").unwrap();
+    let b = rnadraw_core::layout("((...))").unwrap();
+    assert_eq!(a.layout_hash(), b.layout_hash());
+
+    // Float noise below the rounding step leaves the hash unchanged
+    let mut noisy = a.clone();
+    noisy.layout.bases[0].x += 1e-12;
+    assert_eq!(noisy.layout_hash(), a.layout_hash());
+
+    let mut moved = a.clone();
+    moved.layout.bases[0].x += 0.1;
+    assert_ne!(moved.layout_hash(), a.layout_hash());
+    let other = rnadraw_core::layout("((....))").unwrap();
+    assert_ne!(other.layout_hash(), a.layout_hash());
+}
+
+#[test]
+fn test_loop_spacing_enlarges_hairpin() {
+    let default = rnadraw_core::layout("(((........)))").unwrap();
+    let opts = rnadraw_core::LayoutOptions {
+        loop_spacing: 1.5,
+        ..rnadraw_core::LayoutOptions::default()
+    };
+    let spaced = rnadraw_core::layout_with_options("(((........)))", &opts).unwrap();
+    // Stems keep their geometry; the hairpin loop grows
+    let stem = default.loop_index_of_pair(0, 13).unwrap();
+    assert_eq!(
+        default.layout.loops[stem].radius,
+        spaced.layout.loops[stem].radius
+    );
+    let hp = default.loop_index_of_pair(2, 11).unwrap();
+    assert!(spaced.layout.loops[hp].radius > default.layout.loops[hp].radius * 1.3);
+    // Arc spacing still closes the circle
+    let l = &spaced.layout.loops[hp];
+    let total = l.pair_angle + 9.0 * l.arc_angle;
+    assert!((total - 2.0 * std::f64::consts::PI).abs() < 1e-9);
+}
+
+#[test]
+fn test_structure_stats() {
+    let input = "((((...))..((..((...))...))..(.((....)).)))...(((.(...))))";
+    let plain = rnadraw_core::draw_structure(input);
+    assert!(!plain.contains("stats"));
+
+    let opts = rnadraw_core::LayoutOptions {
+        stats: true,
+        ..Default::default()
+    };
+    let json = rnadraw_core::draw_structure_with_options(input, &opts);
+    let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let stats = &v["stats"];
+    assert_eq!(stats["hairpins"], 4);
+    assert_eq!(stats["bulges"], 1);
+    assert_eq!(stats["interior_loops"], 2);
+    assert_eq!(stats["multiloops"], 1);
+    assert_eq!(stats["base_pairs"], 15);
+    // Helices of 2, 2, 2, 2, 1, 2, 3 and 1 pairs
+    assert_eq!(stats["stems"], 8);
+    assert_eq!(stats["longest_stem"], 3);
+}
+
+#[test]
+fn test_backbone_length_hairpin() {
+    let r = rnadraw_core::layout("(((...)))").unwrap();
+    // Four unit arcs around the hairpin loop plus two stem steps on each side
+    let stem_step = (r.layout.bases[1].y - r.layout.bases[0].y).abs();
+    let expected = 4.0 + 4.0 * stem_step;
+    let len = rnadraw_core::backbone_length(&r);
+    assert!(
+        (len - expected).abs() < 1e-9,
+        "got {len}, expected {expected}"
+    );
+    assert!((len - 6.653299832284319).abs() < 1e-9);
+}
+
+#[test]
+fn test_pairing_mask() {
+    assert_eq!(
+        rnadraw_core::pairing_mask("((.+.))").unwrap(),
+        vec![true, true, false, false, true, true]
+    );
+    assert_eq!(
+        rnadraw_core::pairing_mask("..").unwrap(),
+        vec![false, false]
+    );
+    assert!(rnadraw_core::pairing_mask("(.").is_none());
+}
+
+#[test]
+fn test_pair_composition() {
+    let c = rnadraw_core::pair_composition("((((.+.))))", "GaUgA+aUaUC").unwrap();
+    // G-C, A-U, U-A, G-U
+    assert_eq!(
+        c,
+        rnadraw_core::PairComposition {
+            gc: 1,
+            au: 2,
+            gu: 1,
+            mismatch: 0,
+            ambiguous: 0,
+        }
+    );
+
+    let c = rnadraw_core::pair_composition("(((...)))", "GANAAAAAC").unwrap();
+    assert_eq!((c.gc, c.mismatch, c.ambiguous), (1, 1, 1));
+    // T and U are equivalent
+    let c = rnadraw_core::pair_composition("(..)", "AGGT").unwrap();
+    assert_eq!(c.au, 1);
+
+    assert!(rnadraw_core::pair_composition("(..)", "AGG").is_none());
+    assert!(rnadraw_core::pair_composition("(.", "AG").is_none());
+}
+
+#[test]
+fn test_validate_sequence() {
+    assert_eq!(
+        rnadraw_core::validate_sequence("GGAaacc+GGuu", false),
+        Ok(())
+    );
+    assert_eq!(
+        rnadraw_core::validate_sequence("GGTAN", false),
+        Err(vec![(2, 'T'), (4, 'N')])
+    );
+    assert_eq!(
+        rnadraw_core::validate_sequence("GGtAN", true),
+        Err(vec![(4, 'N')])
+    );
+}
+
+#[test]
+fn test_draw_structure_oriented() {
+    let input = "((..((...))..))";
+    assert_eq!(
+        rnadraw_core::draw_structure_oriented(input, false),
+        rnadraw_core::draw_structure(input)
+    );
+
+    let up: serde_json::Value = serde_json::from_str(&rnadraw_core::draw_structure(input)).unwrap();
+    let down: serde_json::Value =
+        serde_json::from_str(&rnadraw_core::draw_structure_oriented(input, true)).unwrap();
+    let num = |v: &serde_json::Value| v.as_f64().unwrap();
+    let (ub, db) = (&up["layout"]["bases"], &down["layout"]["bases"]);
+    for i in 0..15 {
+        assert_eq!(num(&db[i]["x"]), num(&ub[i]["x"]));
+        assert_eq!(num(&db[i]["y"]), -num(&ub[i]["y"]));
+        assert_eq!(num(&db[i]["yt"]), -num(&ub[i]["yt"]));
+    }
+    // Arc endpoints land on the flipped base positions
+    for (segs, ds) in up["segments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .zip(down["segments"].as_array().unwrap())
+    {
+        for (u, d) in segs.as_array().unwrap().iter().zip(ds.as_array().unwrap()) {
+            if d.get("r").is_none() {
+                continue;
+            }
+            let end = |s: &serde_json::Value, t: &str| {
+                let (r, t) = (num(&s["r"]), num(&s[t]));
+                (num(&s["x"]) + r * t.cos(), num(&s["y"]) + r * t.sin())
+            };
+            for t in ["t1", "t2"] {
+                let (ux, uy) = end(u, t);
+                let (dx, dy) = end(d, t);
+                assert!((ux - dx).abs() < 1e-9 && (uy + dy).abs() < 1e-9);
+            }
+        }
+    }
+    assert!(rnadraw_core::draw_structure_oriented("(.", true).is_empty());
+}
+
+#[test]
+fn test_from_partners_feeds_draw_from_pairtable() {
+    let pt = rnadraw_core::from_partners(&[8, 7, 6, -1, -1, -1, 2, 1, 0], &[]).unwrap();
+    let r = rnadraw_core::draw_from_pairtable(&pt).unwrap();
+    let expected = rnadraw_core::layout("(((...)))").unwrap();
+    assert_eq!(
+        serde_json::to_string(&r).unwrap(),
+        serde_json::to_string(&expected).unwrap()
+    );
+
+    // Pseudoknots are representable but not drawable
+    let pk = rnadraw_core::from_partners(&[2, 3, 0, 1], &[]).unwrap();
+    assert!(rnadraw_core::draw_from_pairtable(&pk).is_none());
+}
+
+#[test]
+fn test_draw_structure_tagged() {
+    let input = "((..((...))..))";
+    let plain: serde_json::Value =
+        serde_json::from_str(&rnadraw_core::draw_structure(input)).unwrap();
+    let mut tagged: serde_json::Value =
+        serde_json::from_str(&rnadraw_core::draw_structure_tagged(input)).unwrap();
+
+    let mut kinds = std::collections::HashSet::new();
+    for seg in tagged["segments"]
+        .as_array_mut()
+        .unwrap()
+        .iter_mut()
+        .flat_map(|s| s.as_array_mut().unwrap())
+    {
+        let seg = seg.as_object_mut().unwrap();
+        let kind = seg.remove("type").unwrap();
+        assert_eq!(kind == "arc", seg.contains_key("r"));
+        kinds.insert(kind.as_str().unwrap().to_string());
+    }
+    assert_eq!(kinds.len(), 2);
+    // Apart from the tags, identical to the untagged output
+    assert_eq!(tagged, plain);
+
+    assert!(rnadraw_core::draw_structure_tagged("(.").is_empty());
+}
+
+#[test]
+fn test_has_pseudoknot() {
+    let pk = rnadraw_core::from_partners(&[3, 4, -1, 0, 1], &[]).unwrap();
+    assert!(rnadraw_core::has_pseudoknot(&pk));
+    assert!(rnadraw_core::draw_from_pairtable(&pk).is_none());
+    let nested = rnadraw_core::parse("((.)).(.)").unwrap();
+    assert!(!rnadraw_core::has_pseudoknot(&nested));
+}
+
+#[test]
+fn test_draw_structure_result() {
+    use rnadraw_core::{DrawError, ParseError};
+    assert_eq!(
+        rnadraw_core::draw_structure_result("((...))").unwrap(),
+        rnadraw_core::draw_structure("((...))")
+    );
+    assert_eq!(
+        rnadraw_core::draw_structure_result("((.)"),
+        Err(DrawError::Parse(ParseError::Unmatched('(')))
+    );
+    assert_eq!(
+        rnadraw_core::draw_structure_result("..."),
+        Err(DrawError::NoPairs)
+    );
+    assert_eq!(
+        rnadraw_core::draw_structure_result(""),
+        Err(DrawError::Empty)
+    );
+    let err = rnadraw_core::draw_structure_result("(x)").unwrap_err();
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[test]
+fn test_try_draw_svg() {
+    use rnadraw_core::DrawError;
+    let opts = rnadraw_core::svg::SvgOptions::default();
+    assert_eq!(
+        rnadraw_core::try_draw_svg("((...))+((...))", Some("GGAAACC+GGAAACC"), &opts).unwrap(),
+        rnadraw_core::draw_svg("((...))+((...))", Some("GGAAACC+GGAAACC"), &opts)
+    );
+    assert!(matches!(
+        rnadraw_core::try_draw_svg("((x))", None, &opts),
+        Err(DrawError::Parse(_))
+    ));
+    assert_eq!(
+        rnadraw_core::try_draw_svg("", None, &opts),
+        Err(DrawError::Empty)
+    );
+    assert_eq!(
+        rnadraw_core::try_draw_svg("...", None, &opts),
+        Err(DrawError::NoPairs)
+    );
+    assert_eq!(rnadraw_core::draw_svg("...", None, &opts), "");
+
+    let err = rnadraw_core::try_draw_svg("(((...)))", Some("GGGAAACCCA"), &opts).unwrap_err();
+    assert_eq!(
+        err,
+        DrawError::SequenceLength {
+            sequence: 10,
+            bases: 9
+        }
+    );
+    assert_eq!(err.to_string(), "sequence length 10 != 9 bases");
+    assert_eq!(
+        rnadraw_core::draw_svg("(((...)))", Some("GGGAAACCCA"), &opts),
+        ""
+    );
+}
+
+#[test]
+fn test_draw_svg_checked() {
+    use rnadraw_core::DrawError;
+    let opts = rnadraw_core::svg::SvgOptions::default();
+    assert_eq!(
+        rnadraw_core::draw_svg_checked("((...))+((...))", Some("GGAAACC+GGAAACC"), &opts).unwrap(),
+        rnadraw_core::draw_svg("((...))+((...))", Some("GGAAACC+GGAAACC"), &opts)
+    );
+    let err = rnadraw_core::draw_svg_checked("(((...)))", Some("GGGAAACCCA"), &opts).unwrap_err();
+    assert_eq!(
+        err,
+        DrawError::SequenceLength {
+            sequence: 10,
+            bases: 9
+        }
+    );
+    assert_eq!(
+        rnadraw_core::draw_svg_checked("...", None, &opts),
+        Err(DrawError::NoPairs)
+    );
+}
+
+#[test]
+fn test_draw_svg_to() {
+    let opts = rnadraw_core::svg::SvgOptions::default();
+    let mut out = String::new();
+    rnadraw_core::draw_svg_to(&mut out, "((..((...))..))", None, &opts).unwrap();
+    assert_eq!(out, rnadraw_core::draw_svg("((..((...))..))", None, &opts));
+
+    let mut out = String::new();
+    rnadraw_core::draw_svg_to(&mut out, "((", None, &opts).unwrap();
+    assert!(out.is_empty());
+
+    // A sequence that doesn't fit the structure is rejected like by draw_svg
+    let mut out = String::new();
+    rnadraw_core::draw_svg_to(&mut out, "((...))", Some("GGAAACCA"), &opts).unwrap();
+    assert!(out.is_empty());
+}
+
+#[test]
+fn test_draw_from_pairtable_matches_parse() {
+    let pt = rnadraw_core::PairTable {
+        pairs: vec![5, 4, 2, 3, 1, 0],
+        nicks: vec![0, 3],
+        n_bases: 6,
+    };
+    let r = rnadraw_core::draw_from_pairtable(&pt).unwrap();
+    let expected = rnadraw_core::layout("((.+.))").unwrap();
+    assert_eq!(
+        serde_json::to_string(&r).unwrap(),
+        serde_json::to_string(&expected).unwrap()
+    );
+}
+
+#[test]
+fn test_draw_from_pairtable_rejects_invalid() {
+    let table = |pairs: Vec<usize>, nicks: Vec<usize>| rnadraw_core::PairTable {
+        n_bases: pairs.len(),
+        pairs,
+        nicks,
+    };
+    // Asymmetric
+    assert!(rnadraw_core::draw_from_pairtable(&table(vec![3, 1, 2, 1], vec![0])).is_none());
+    // Crossing
+    assert!(rnadraw_core::draw_from_pairtable(&table(vec![2, 3, 0, 1], vec![0])).is_none());
+    // Missing leading nick
+    assert!(rnadraw_core::draw_from_pairtable(&table(vec![1, 0], vec![])).is_none());
+    // Out-of-range partner
+    assert!(rnadraw_core::draw_from_pairtable(&table(vec![7, 1], vec![0])).is_none());
+}
+
+#[test]
+fn test_strands_match_nicks() {
+    let r = rnadraw_core::layout("((.+..+.))").unwrap();
+    assert_eq!(r.nicks, vec![0, 3, 5]);
+    assert_eq!(r.strands(), vec![(0, 3), (3, 5), (5, 8)]);
+
+    let r = rnadraw_core::layout("(((...)))").unwrap();
+    assert_eq!(r.strands(), vec![(0, 9)]);
+}
+
+#[test]
+fn test_draw_svg_with_rotation() {
+    let opts = rnadraw_core::svg::SvgOptions::default();
+    let (svg, angle) = rnadraw_core::draw_svg_with_rotation("((...))", None, &opts, None);
+    assert_eq!(svg, rnadraw_core::draw_svg("((...))", None, &opts));
+    assert!(angle != 0.0);
+
+    // Reusing the angle reproduces the output; a forced angle overrides alignment
+    let (again, used) = rnadraw_core::draw_svg_with_rotation("((...))", None, &opts, Some(angle));
+    assert_eq!((again, used), (svg, angle));
+    let (_, used) = rnadraw_core::draw_svg_with_rotation("((...))", None, &opts, Some(0.25));
+    assert_eq!(used, 0.25);
+
+    let unaligned = rnadraw_core::svg::SvgOptions {
+        align_stem: false,
+        ..Default::default()
+    };
+    let (_, used) = rnadraw_core::draw_svg_with_rotation("((...))", None, &unaligned, None);
+    assert_eq!(used, 0.0);
+    assert_eq!(
+        rnadraw_core::draw_svg_with_rotation("((", None, &opts, None),
+        (String::new(), 0.0)
+    );
+    assert_eq!(
+        rnadraw_core::draw_svg_with_rotation("((...))", Some("GGAAACCA"), &opts, None),
+        (String::new(), 0.0)
+    );
+}
+
+#[test]
+fn test_draw_result_rotate() {
+    use rnadraw_core::Segment;
+    let a = rnadraw_core::layout("((...))").unwrap();
+    let mut r = a.clone();
+    r.rotate(std::f64::consts::FRAC_PI_2);
+    for (b, o) in r.layout.bases.iter().zip(&a.layout.bases) {
+        assert!((b.x + o.y).abs() < 1e-9 && (b.y - o.x).abs() < 1e-9);
+    }
+
+    // Arcs keep their sweep: endpoints rotate with their centers
+    for (seg, orig) in r.segments.iter().flatten().zip(a.segments.iter().flatten()) {
+        if let (Segment::Arc(arc), Segment::Arc(o)) = (seg, orig) {
+            let (x, y) = arc.point(arc.t1);
+            let (ox, oy) = o.point(o.t1);
+            assert!((x + oy).abs() < 1e-9 && (y - ox).abs() < 1e-9);
+        }
+    }
+
+    r.rotate(-std::f64::consts::FRAC_PI_2);
+    for (b, o) in r.layout.bases.iter().zip(&a.layout.bases) {
+        assert!((b.x - o.x).abs() < 1e-9 && (b.y - o.y).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_draw_result_translate() {
+    use rnadraw_core::Segment;
+    let a = rnadraw_core::layout("((...))").unwrap();
+    let mut r = a.clone();
+    r.translate(3.0, -2.0);
+    for (b, o) in r.layout.bases.iter().zip(&a.layout.bases) {
+        assert_eq!((b.x, b.y), (o.x + 3.0, o.y - 2.0));
+        assert_eq!((b.xt, b.yt), (o.xt + 3.0, o.yt - 2.0));
+    }
+    for (l, o) in r.layout.loops.iter().zip(&a.layout.loops) {
+        assert_eq!((l.x, l.y), (o.x + 3.0, o.y - 2.0));
+    }
+    for (seg, orig) in r.segments.iter().flatten().zip(a.segments.iter().flatten()) {
+        match (seg, orig) {
+            (Segment::Arc(arc), Segment::Arc(o)) => {
+                assert_eq!((arc.x, arc.y), (o.x + 3.0, o.y - 2.0));
+                assert_eq!((arc.t1, arc.t2), (o.t1, o.t2));
+            }
+            (Segment::Line(line), Segment::Line(o)) => {
+                assert_eq!((line.x1, line.y1), (o.x1 + 3.0, o.y1 - 2.0));
+            }
+            _ => unreachable!(),
+        }
+    }
+}
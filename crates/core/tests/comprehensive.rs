@@ -1,3 +1,5 @@
+#![allow(clippy::single_match, clippy::collapsible_match)]
+
 use std::collections::HashMap;
 use std::fs;
 
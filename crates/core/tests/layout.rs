@@ -0,0 +1,748 @@
+use rnadraw_core::{LayoutOptions, LoopKind, Segment, SegmentMode};
+
+fn pair_midpoint(r: &rnadraw_core::DrawResult, i: usize) -> (f64, f64) {
+    let j = r.pairs[i];
+    let (bi, bj) = (&r.layout.bases[i], &r.layout.bases[j]);
+    ((bi.x + bj.x) / 2.0, (bi.y + bj.y) / 2.0)
+}
+
+#[test]
+fn test_loop_kinds() {
+    // 5' bulge, interior loop, multiloop, hairpins
+    let r = rnadraw_core::layout("((.((..((...))..((...))))))").unwrap();
+    let kind = |i, j| r.layout.loops[r.loop_index_of_pair(i, j).unwrap()].kind;
+    assert_eq!(r.layout.loops[0].kind, LoopKind::External);
+    assert_eq!(kind(0, 26), LoopKind::Stack);
+    assert_eq!(kind(1, 25), LoopKind::Bulge);
+    assert_eq!(kind(3, 24), LoopKind::Stack);
+    assert_eq!(kind(4, 23), LoopKind::Multi);
+    assert_eq!(kind(7, 13), LoopKind::Stack);
+    assert_eq!(kind(8, 12), LoopKind::Hairpin);
+
+    let r = rnadraw_core::layout("((..((...))..))").unwrap();
+    assert_eq!(
+        r.layout.loops[r.loop_index_of_pair(1, 13).unwrap()].kind,
+        LoopKind::Interior
+    );
+    // Not part of the default JSON schema
+    assert!(!rnadraw_core::draw_structure("((..((...))..))").contains("\"kind\""));
+}
+
+#[test]
+fn test_straight_bulge_keeps_helices_collinear() {
+    // Helix (0..2) — 2-nt bulge on the 5' side — helix (5..7)
+    let input = "(((..(((...))))))";
+    let opts = LayoutOptions {
+        straight_bulges: true,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+    let bulge = r.loop_index_of_pair(2, 14).unwrap();
+    assert_eq!(r.layout.loops[bulge].kind, LoopKind::Bulge);
+
+    // Pair midpoints on both sides of the bulge lie on one line
+    let pts: Vec<(f64, f64)> = [0, 1, 2, 5, 6, 7]
+        .iter()
+        .map(|&i| pair_midpoint(&r, i))
+        .collect();
+    let (x0, y0) = pts[0];
+    let (dx, dy) = (pts[5].0 - x0, pts[5].1 - y0);
+    let len = (dx * dx + dy * dy).sqrt();
+    for &(x, y) in &pts[1..5] {
+        let dist = ((x - x0) * dy - (y - y0) * dx).abs() / len;
+        assert!(dist < 1e-9, "pair midpoint off helix axis by {dist}");
+    }
+
+    // Unpaired bases are evenly spaced on the bulge side
+    let l = &r.layout.loops[bulge];
+    let step = l.radius * l.arc_angle;
+    assert!((step - 1.0).abs() < 1e-6, "bulge arc spacing {step}");
+
+    // Empty side (base 13 → 14) is a straight link
+    assert!(matches!(r.segments[14][0], Segment::Line(_)));
+    assert!(matches!(r.segments[13][1], Segment::Line(_)));
+}
+
+#[test]
+fn test_straight_bulges_off_by_default() {
+    let input = "(((..(((...))))))";
+    let a = rnadraw_core::draw_structure(input);
+    let b = rnadraw_core::draw_structure_with_options(input, &LayoutOptions::default());
+    assert_eq!(a, b);
+    let r = rnadraw_core::layout(input).unwrap();
+    assert!(matches!(r.segments[14][0], Segment::Arc(_)));
+}
+
+/// (base end, midpoint end) of a half-segment; arcs run from base to midpoint
+/// for incoming halves and from midpoint to base for outgoing ones.
+fn half_segment_ends(seg: &Segment, incoming: bool) -> ((f64, f64), (f64, f64)) {
+    match seg {
+        Segment::Line(l) => ((l.x, l.y), (l.x1, l.y1)),
+        Segment::Arc(a) => {
+            let (p1, p2) = (a.point(a.t1), a.point(a.t2));
+            if incoming { (p1, p2) } else { (p2, p1) }
+        }
+    }
+}
+
+fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+    (a.0 - b.0).hypot(a.1 - b.1) < 1e-9
+}
+
+#[test]
+fn test_segment_modes() {
+    let input = "((.((...))..((...))))";
+    for mode in [SegmentMode::AllArcs, SegmentMode::AllLines] {
+        let opts = LayoutOptions {
+            segment_mode: mode,
+            ..LayoutOptions::default()
+        };
+        let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+        let bases = &r.layout.bases;
+        for (i, segs) in r.segments.iter().enumerate() {
+            for seg in segs {
+                let is_arc = matches!(seg, Segment::Arc(_));
+                assert_eq!(is_arc, mode == SegmentMode::AllArcs, "{mode:?} base {i}");
+            }
+            // Links between consecutive bases (the strand ends are nick stubs)
+            if i + 1 < bases.len() {
+                let (from, mid_a) = half_segment_ends(&segs[1], false);
+                let (to, mid_b) = half_segment_ends(&r.segments[i + 1][0], true);
+                assert!(close(from, (bases[i].x, bases[i].y)), "{mode:?} {i}");
+                assert!(close(to, (bases[i + 1].x, bases[i + 1].y)), "{mode:?} {i}");
+                assert!(close(mid_a, mid_b), "{mode:?} link {i}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_large_multiloop_closes() {
+    // 24 hairpin branches off one multiloop
+    let input = format!("({})", "((...))".repeat(24));
+    let r = rnadraw_core::layout(&input).unwrap();
+    let ml = &r.layout.loops[r.loop_index_of_pair(0, input.len() - 1).unwrap()];
+    assert!(ml.radius.is_finite() && ml.radius > 0.5);
+    assert!(ml.pair_angle < std::f64::consts::PI);
+    // 25 pairs and no unpaired bases: the circle closes exactly
+    let total = 25.0 * (ml.pair_angle + ml.arc_angle);
+    assert!(
+        (total - 2.0 * std::f64::consts::PI).abs() < 1e-9,
+        "total {total}"
+    );
+
+    // Bases on the multiloop circle stay apart
+    let on_loop: Vec<_> = ml
+        .pairs
+        .iter()
+        .flat_map(|p| [p.first, p.last])
+        .map(|b| (r.layout.bases[b].x, r.layout.bases[b].y))
+        .collect();
+    for (i, a) in on_loop.iter().enumerate() {
+        for b in &on_loop[i + 1..] {
+            assert!((a.0 - b.0).hypot(a.1 - b.1) > 0.5);
+        }
+    }
+}
+
+#[test]
+fn test_outward_direction() {
+    let r = rnadraw_core::layout("((...))").unwrap();
+    for i in 0..7 {
+        let (dx, dy) = rnadraw_core::outward_direction(&r, i);
+        assert!((dx.hypot(dy) - 1.0).abs() < 1e-12);
+    }
+
+    // Unpaired: along the label offset from the loop center
+    let b = &r.layout.bases[3];
+    let (dx, dy) = rnadraw_core::outward_direction(&r, 3);
+    let (ox, oy) = (b.xt - b.x, b.yt - b.y);
+    assert!((dx - ox / 0.5).abs() < 1e-9 && (dy - oy / 0.5).abs() < 1e-9);
+
+    // Paired: away from the partner, so the two sides of a pair are opposite
+    let (ax, ay) = rnadraw_core::outward_direction(&r, 0);
+    let (bx, by) = rnadraw_core::outward_direction(&r, 6);
+    assert!((ax + bx).abs() < 1e-9 && (ay + by).abs() < 1e-9);
+    let (b0, b6) = (&r.layout.bases[0], &r.layout.bases[6]);
+    assert!((b0.x - b6.x) * ax + (b0.y - b6.y) * ay > 0.0);
+}
+
+#[test]
+fn test_draw_unstructured() {
+    assert!(rnadraw_core::layout("...").is_none());
+
+    let opts = LayoutOptions {
+        draw_unstructured: true,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(".", &opts).unwrap();
+    assert_eq!(r.layout.bases.len(), 1);
+    assert_eq!((r.layout.bases[0].x, r.layout.bases[0].y), (0.0, 0.0));
+
+    let r = rnadraw_core::layout_with_options("....", &opts).unwrap();
+    let xs: Vec<f64> = r.layout.bases.iter().map(|b| b.x).collect();
+    assert_eq!(xs, vec![-0.75, -0.25, 0.25, 0.75]);
+    assert!(r.layout.bases.iter().all(|b| b.y == 0.0));
+    assert!(
+        r.segments
+            .iter()
+            .flatten()
+            .all(|s| matches!(s, Segment::Line(_)))
+    );
+    // The backbone spans from the first base to the last
+    assert!((rnadraw_core::backbone_length(&r) - 1.5).abs() < 1e-12);
+
+    // Structures with pairs are unaffected
+    let a = rnadraw_core::layout_with_options("((...))", &opts).unwrap();
+    let b = rnadraw_core::layout("((...))").unwrap();
+    assert_eq!(a.layout.bases[3].x, b.layout.bases[3].x);
+
+    let svg = rnadraw_core::draw_svg(
+        "...",
+        None,
+        &rnadraw_core::svg::SvgOptions {
+            layout: opts,
+            ..Default::default()
+        },
+    );
+    assert_eq!(svg.matches("<circle").count(), 3);
+}
+
+#[test]
+fn test_unpaired_strands() {
+    // Multi-strand structures without pairs need no option
+    let r = rnadraw_core::layout("...+..").unwrap();
+    let pos: Vec<(f64, f64)> = r.layout.bases.iter().map(|b| (b.x, b.y)).collect();
+    assert_eq!(
+        pos,
+        vec![
+            (-0.5, 1.0),
+            (0.0, 1.0),
+            (0.5, 1.0),
+            (-0.25, -1.0),
+            (0.25, -1.0)
+        ]
+    );
+    // No backbone link across the nick
+    assert!((rnadraw_core::backbone_length(&r) - 1.5).abs() < 1e-12);
+
+    let r = rnadraw_core::layout(".+.").unwrap();
+    assert_eq!(r.layout.bases[0].y - r.layout.bases[1].y, 2.0);
+    let svg = rnadraw_core::draw_svg(".+.", None, &Default::default());
+    assert_eq!(svg.matches("<circle").count(), 2);
+}
+
+#[test]
+fn test_straight_tails() {
+    let input = "...(((...)))..";
+    let opts = LayoutOptions {
+        straight_tails: true,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+    let bases = &r.layout.bases;
+    let p = |i: usize| (bases[i].x, bases[i].y);
+    let collinear = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| {
+        ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).abs() < 1e-9
+    };
+
+    // 5' tail continues the (3, 11) pair bond outward from base 3
+    for i in 0..3 {
+        assert!(collinear(p(11), p(3), p(i)), "5' base {i}");
+    }
+    let d = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1);
+    assert!(d(p(0), p(11)) > d(p(2), p(11)));
+    // 3' tail continues the same bond from base 11
+    for i in 12..14 {
+        assert!(collinear(p(3), p(11), p(i)), "3' base {i}");
+    }
+    assert!(d(p(13), p(3)) > d(p(12), p(3)));
+
+    // Tail links are straight lines; the hairpin keeps its arcs
+    for i in [0, 1, 2, 12, 13] {
+        assert!(r.segments[i].iter().all(|s| matches!(s, Segment::Line(_))));
+    }
+    assert!(matches!(r.segments[3][0], Segment::Line(_)));
+    assert!(matches!(r.segments[7][0], Segment::Arc(_)));
+
+    // Unchanged by default, and tails broken by a nick stay on the circle
+    let plain = rnadraw_core::layout(input).unwrap();
+    assert!(matches!(plain.segments[1][0], Segment::Arc(_)));
+    let r = rnadraw_core::layout_with_options("..+.(((...)))", &opts).unwrap();
+    assert!(matches!(r.segments[1][0], Segment::Arc(_)));
+}
+
+/// Rotated midpoints of pairs (i, j), (i+1, j-1), ... must share an x coordinate.
+fn assert_stem_vertical(input: &str, i: usize, j: usize, depth: usize) {
+    let opts = rnadraw_core::svg::SvgOptions::default();
+    let (_, angle) = rnadraw_core::draw_svg_with_rotation(input, None, &opts, None);
+    let r = rnadraw_core::layout(input).unwrap();
+    let (cos, sin) = (angle.cos(), angle.sin());
+    let xs: Vec<f64> = (0..depth)
+        .map(|k| {
+            let (x, y) = pair_midpoint(&r, i + k);
+            assert_eq!(r.pairs[i + k], j - k);
+            x * cos - y * sin
+        })
+        .collect();
+    for x in &xs {
+        assert!((x - xs[0]).abs() < 1e-9, "{input}: {xs:?}");
+    }
+}
+
+#[test]
+fn test_stem_alignment_uses_outermost_stem() {
+    // Long leader before a hairpin
+    assert_stem_vertical("..........((((....))))", 10, 21, 4);
+    // A small 5' hairpin must not decide the orientation of the large one
+    assert_stem_vertical("..((...))....((((((....))))))", 13, 28, 6);
+}
+
+#[test]
+fn test_coaxial_stacking() {
+    // Three-way junction: parent helix (2, 23), children (5, 11) and (14, 20)
+    let input = "(((..((...))..((...))..)))";
+    let default = rnadraw_core::layout(input).unwrap();
+    let ml = default.loop_index_of_pair(2, 23).unwrap();
+    let pair_angle = |r: &rnadraw_core::DrawResult, i: usize| {
+        let l = &r.layout.loops[ml];
+        let (x, y) = pair_midpoint(r, i);
+        (y - l.y).atan2(x - l.x)
+    };
+    let opposite = |r: &rnadraw_core::DrawResult, i: usize, j: usize| {
+        let d = (pair_angle(r, i) - pair_angle(r, j)).rem_euclid(2.0 * std::f64::consts::PI);
+        (d - std::f64::consts::PI).abs() < 1e-9
+    };
+    assert!(!opposite(&default, 5, 14));
+
+    for (a, b) in [(5, 14), (2, 20), (11, 23)] {
+        let opts = LayoutOptions {
+            coaxial: vec![(a, b)],
+            ..LayoutOptions::default()
+        };
+        let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+        let (pa, pb) = (a.min(r.pairs[a]), b.min(r.pairs[b]));
+        assert!(opposite(&r, pa, pb), "coaxial ({a}, {b})");
+        // Pair bonds keep unit length and the loop keeps its radius
+        for i in [2, 5, 14] {
+            let (bi, bj) = (&r.layout.bases[i], &r.layout.bases[r.pairs[i]]);
+            assert!(((bi.x - bj.x).hypot(bi.y - bj.y) - 1.0).abs() < 1e-9);
+        }
+        assert_eq!(r.layout.loops[ml].radius, default.layout.loops[ml].radius);
+    }
+
+    // Pairs that don't share a loop are ignored
+    let opts = LayoutOptions {
+        coaxial: vec![(0, 6)],
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+    for (b, d) in r.layout.bases.iter().zip(&default.layout.bases) {
+        assert_eq!((b.x, b.y), (d.x, d.y));
+    }
+}
+
+#[test]
+fn test_max_loop_radius() {
+    let input = format!("(.{}.)", "((...)).".repeat(8));
+    let default = rnadraw_core::layout(&input).unwrap();
+    let ml = default.loop_index_of_pair(0, input.len() - 1).unwrap();
+    let natural = default.layout.loops[ml].radius;
+    assert!(natural > 2.0);
+
+    let opts = LayoutOptions {
+        max_loop_radius: Some(2.0),
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(&input, &opts).unwrap();
+    let l = &r.layout.loops[ml];
+    assert!((l.radius - 2.0).abs() < 1e-12);
+    // Nine pairs and 8 + 2 unpaired bases (19 steps) still close the circle
+    assert!((9.0 * l.pair_angle + 19.0 * l.arc_angle - 2.0 * std::f64::consts::PI).abs() < 1e-9);
+    for (i, &j) in r.pairs.iter().enumerate().filter(|&(i, &j)| i < j) {
+        let (bi, bj) = (&r.layout.bases[i], &r.layout.bases[j]);
+        assert!(((bi.x - bj.x).hypot(bi.y - bj.y) - 1.0).abs() < 1e-9);
+    }
+    // Hairpins are below the cap and keep their size
+    let hp = r.loop_index_of_pair(3, 7).unwrap();
+    assert_eq!(r.layout.loops[hp].radius, default.layout.loops[hp].radius);
+
+    // A cap too small for the pairs alone stops at the closing radius
+    let opts = LayoutOptions {
+        max_loop_radius: Some(0.1),
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(&input, &opts).unwrap();
+    let l = &r.layout.loops[ml];
+    assert!(9.0 * l.pair_angle <= 2.0 * std::f64::consts::PI + 1e-9);
+    assert!(l.arc_angle >= -1e-12);
+}
+
+#[test]
+fn test_min_radius() {
+    let input = "(((...))((...)))";
+    let default = rnadraw_core::layout(input).unwrap();
+    let ml = default.loop_index_of_pair(0, 15).unwrap();
+    assert!(default.layout.loops[ml].radius < 1.0);
+
+    let opts = LayoutOptions {
+        min_radius: 1.0,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+    let l = &r.layout.loops[ml];
+    assert_eq!(l.radius, 1.0);
+    // Angles follow the floored radius and the three arcs absorb the gap
+    assert!((l.pair_angle - 2.0 * 0.5f64.asin()).abs() < 1e-12);
+    assert!((3.0 * l.pair_angle + 3.0 * l.arc_angle - 2.0 * std::f64::consts::PI).abs() < 1e-9);
+    for (i, &j) in r.pairs.iter().enumerate().filter(|&(i, &j)| i < j) {
+        let (bi, bj) = (&r.layout.bases[i], &r.layout.bases[j]);
+        assert!(((bi.x - bj.x).hypot(bi.y - bj.y) - 1.0).abs() < 1e-9);
+    }
+    // Hairpins are floored too; stacked pairs keep their fixed radius
+    let hp = r.loop_index_of_pair(2, 6).unwrap();
+    assert_eq!(r.layout.loops[hp].radius, 1.0);
+    let stem = r.loop_index_of_pair(1, 7).unwrap();
+    assert_eq!(
+        r.layout.loops[stem].radius,
+        default.layout.loops[stem].radius
+    );
+}
+
+#[test]
+fn test_min_base_spacing() {
+    let input = format!("(({}))", ".".repeat(15));
+    let step = |r: &rnadraw_core::DrawResult, i: usize| {
+        let (a, b) = (&r.layout.bases[i], &r.layout.bases[i + 1]);
+        (a.x - b.x).hypot(a.y - b.y)
+    };
+    let default = rnadraw_core::layout(&input).unwrap();
+    assert!((1..17).all(|i| step(&default, i) < 1.2));
+
+    let opts = LayoutOptions {
+        min_base_spacing: Some(1.2),
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(&input, &opts).unwrap();
+    let hp = r.loop_index_of_pair(1, 17).unwrap();
+    assert!(r.layout.loops[hp].radius > default.layout.loops[hp].radius);
+    for i in 1..17 {
+        assert!(step(&r, i) > 1.2 - 1e-9, "{i}: {}", step(&r, i));
+    }
+    // Just wide enough: the steps sit on the bound
+    assert!((step(&r, 5) - 1.2).abs() < 1e-9);
+    // The closing pair keeps unit length
+    let (a, b) = (&r.layout.bases[1], &r.layout.bases[17]);
+    assert!(((a.x - b.x).hypot(a.y - b.y) - 1.0).abs() < 1e-9);
+
+    // A bound the default layout already meets changes nothing
+    let opts = LayoutOptions {
+        min_base_spacing: Some(0.5),
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(&input, &opts).unwrap();
+    assert_eq!(r.layout.loops[hp].radius, default.layout.loops[hp].radius);
+}
+
+#[test]
+fn test_min_base_distance_matches_brute_force() {
+    let brute = |r: &rnadraw_core::DrawResult| {
+        let b = &r.layout.bases;
+        let mut best = f64::INFINITY;
+        for i in 0..b.len() {
+            for j in i + 1..b.len() {
+                best = best.min((b[i].x - b[j].x).hypot(b[i].y - b[j].y));
+            }
+        }
+        best
+    };
+    let big = "((((...))..((....))..))...".repeat(80);
+    for input in [
+        "(((...)))",
+        "((..((...))..((...))..))",
+        "((((+))))",
+        big.as_str(),
+    ] {
+        let r = rnadraw_core::layout(input).unwrap();
+        let d = rnadraw_core::min_base_distance(&r);
+        assert!((d - brute(&r)).abs() < 1e-12, "{input}");
+        assert!(d > 0.0);
+    }
+
+    let opts = LayoutOptions {
+        draw_unstructured: true,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(".", &opts).unwrap();
+    assert_eq!(rnadraw_core::min_base_distance(&r), f64::INFINITY);
+}
+
+#[test]
+fn test_root_angle_rotates_layout() {
+    let input = "..((..((...))..((...))))..";
+    let default = rnadraw_core::layout(input).unwrap();
+    let theta = 0.7;
+    let opts = LayoutOptions {
+        root_angle: std::f64::consts::FRAC_PI_2 + theta,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+    // Same shape rotated by theta (up to the recentering translation)
+    let (c, s) = (theta.cos(), theta.sin());
+    let (d0, r0) = (&default.layout.bases[0], &r.layout.bases[0]);
+    for (d, b) in default.layout.bases.iter().zip(&r.layout.bases) {
+        let (dx, dy) = (d.x - d0.x, d.y - d0.y);
+        let expected = (dx * c - dy * s, dx * s + dy * c);
+        assert!(close((b.x - r0.x, b.y - r0.y), expected));
+    }
+}
+
+#[test]
+fn test_elliptical_loops() {
+    let input = "((..((...))...((....))..(((.....)))..))";
+    let default = rnadraw_core::layout(input).unwrap();
+    assert!(default.layout.loops.iter().all(|l| l.ellipse.is_none()));
+
+    let opts = LayoutOptions {
+        elliptical_loops: 0.6,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+    let (b, d) = (&r.layout.bases, &default.layout.bases);
+    let dist =
+        |b: &[rnadraw_core::Base], i: usize, j: usize| (b[i].x - b[j].x).hypot(b[i].y - b[j].y);
+
+    // Multiloop and hairpins become ellipses flattened towards their parent helix
+    let ml = r.loop_index_of_pair(1, 37).unwrap();
+    for li in [ml, r.loop_index_of_pair(5, 9).unwrap()] {
+        let l = &r.layout.loops[li];
+        let e = l.ellipse.expect("elliptical");
+        assert!((l.radius - 0.6 * e.ry).abs() < 1e-9);
+    }
+    // Stems stay circular
+    assert!(
+        r.layout.loops[r.loop_index_of_pair(0, 38).unwrap()]
+            .ellipse
+            .is_none()
+    );
+
+    // Pair bonds and backbone steps keep their lengths
+    for i in 0..b.len() {
+        let j = r.pairs[i];
+        if i < j {
+            assert!((dist(b, i, j) - 1.0).abs() < 1e-6, "pair {i}-{j}");
+        }
+        if i + 1 < b.len() {
+            assert!(
+                (dist(b, i, i + 1) - dist(d, i, i + 1)).abs() < 1e-6,
+                "step {i}"
+            );
+        }
+    }
+    // Bases sit on their loop's outline and the backbone pieces join up
+    for (i, base) in b.iter().enumerate() {
+        let l = &r.layout.loops[base.loop1];
+        assert!(close(l.point(base.angle1), (base.x, base.y)), "base {i}");
+        let (start, _) = half_segment_ends(&r.segments[i][0], true);
+        assert!(close(start, (base.x, base.y)), "segment {i}");
+        if i + 1 < b.len() {
+            let (_, end) = half_segment_ends(&r.segments[i][1], false);
+            let (_, next) = half_segment_ends(&r.segments[i + 1][0], true);
+            assert!((end.0 - next.0).hypot(end.1 - next.1) < 1e-6, "join {i}");
+        }
+    }
+    // The multiloop is shallower along its closing helix
+    let depth = |r: &rnadraw_core::DrawResult| {
+        let l = &r.layout.loops[ml];
+        let m = pair_midpoint(r, 1);
+        let (ux, uy) = ((l.x - m.0) / l.height, (l.y - m.1) / l.height);
+        let bases = &r.layout.bases;
+        (2..37)
+            .filter(|&i| bases[i].loop1 == ml || bases[i].loop2 == ml)
+            .map(|i| (bases[i].x - m.0) * ux + (bases[i].y - m.1) * uy)
+            .fold(0.0, f64::max)
+    };
+    assert!(depth(&r) < depth(&default));
+
+    let svg = rnadraw_core::svg::render(&r, None, &Default::default());
+    assert!(svg.contains("<path d=\"M"));
+}
+
+#[test]
+fn test_elliptical_loops_keep_spacing() {
+    // Small hairpins and interior loops between close branches
+    let input = "..(.)..(.+(.).)+.(+(.).)((..)..)";
+    let default = rnadraw_core::min_base_distance(&rnadraw_core::layout(input).unwrap());
+    for aspect in [0.3, 0.5, 2.0] {
+        let opts = LayoutOptions {
+            elliptical_loops: aspect,
+            ..LayoutOptions::default()
+        };
+        let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+        let d = rnadraw_core::min_base_distance(&r);
+        assert!(d >= default - 1e-9, "aspect {aspect}: {d} < {default}");
+    }
+}
+
+#[test]
+fn test_duplex_strands_antiparallel() {
+    // Each base sits level with its partner, so the second strand already runs
+    // 3′→5′ alongside the first; reversing its traversal would detach the pairs.
+    let r = rnadraw_core::layout("(((+)))").unwrap();
+    let b = &r.layout.bases;
+    for i in 0..3 {
+        let j = r.pairs[i];
+        assert!((b[i].y - b[j].y).abs() < 1e-9);
+        assert!(((b[j].x - b[i].x) - 1.0).abs() < 1e-9);
+    }
+    assert!(b[0].y < b[1].y && b[1].y < b[2].y);
+    assert!(b[3].y > b[4].y && b[4].y > b[5].y);
+
+    // Pointing the stem right reads as a ladder: first strand 5′→3′ left to right
+    // on top, second strand 3′→5′ left to right below
+    let opts = LayoutOptions {
+        root_angle: 0.0,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options("(((+)))", &opts).unwrap();
+    let b = &r.layout.bases;
+    for i in 0..3 {
+        let j = r.pairs[i];
+        assert!((b[i].x - b[j].x).abs() < 1e-9);
+        assert!(((b[i].y - b[j].y) - 1.0).abs() < 1e-9);
+    }
+    assert!(b[0].x < b[1].x && b[1].x < b[2].x);
+    assert!(b[5].x < b[4].x && b[4].x < b[3].x);
+}
+
+#[test]
+fn test_loop_metrics() {
+    use rnadraw_core::geometry::loop_metrics;
+
+    let r = rnadraw_core::layout("((((.....))))").unwrap();
+    let hairpin = r.loop_index_of_pair(3, 9).unwrap();
+    let m = loop_metrics(&r, hairpin).unwrap();
+    let l = &r.layout.loops[hairpin];
+    assert_eq!(m.radius, l.radius);
+    let tau = 2.0 * std::f64::consts::PI;
+    assert!((m.circumference - tau * l.radius).abs() < 1e-9);
+    // Closing pair plus six unit arcs around the five unpaired bases close the circle
+    assert!((m.pair_angle + 6.0 * m.arc_angle - tau).abs() < 1e-9);
+    // Closest neighbors are the unpaired steps, whose chord spans one arc_angle
+    let step = 2.0 * m.radius * (m.arc_angle / 2.0).sin();
+    assert!((m.min_base_spacing - step).abs() < 1e-9);
+
+    // Stacked pairs sit a full bond apart across the stem loop
+    let stack = r.loop_index_of_pair(1, 11).unwrap();
+    let m = loop_metrics(&r, stack).unwrap();
+    assert!(m.min_base_spacing > 0.0 && m.min_base_spacing <= 1.0 + 1e-9);
+
+    assert!(loop_metrics(&r, r.layout.loops.len()).is_none());
+
+    // Elliptical loops report their actual spacing and perimeter
+    let input = "((((....((((....))))....((((....))))....))))";
+    let default = rnadraw_core::layout(input).unwrap();
+    let ml = default.loop_index_of_pair(3, 40).unwrap();
+    let round = loop_metrics(&default, ml).unwrap();
+    for aspect in [0.5, 2.0] {
+        let opts = LayoutOptions {
+            elliptical_loops: aspect,
+            ..LayoutOptions::default()
+        };
+        let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+        let m = loop_metrics(&r, ml).unwrap();
+        assert!(m.min_base_spacing.is_finite() && m.min_base_spacing > 0.0);
+        assert!(r.layout.loops[ml].ellipse.is_some());
+        assert!((m.circumference - round.circumference).abs() > 1e-3);
+    }
+
+    // Straightened tails stay part of the external loop
+    let opts = LayoutOptions {
+        straight_tails: true,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options("...(((...)))...", &opts).unwrap();
+    assert!(loop_metrics(&r, 0).unwrap().min_base_spacing.is_finite());
+}
+
+/// Stacked stems are placed loop by loop; the deepest hairpin must not drift off
+/// the helix axis.
+#[test]
+fn test_deep_stem_collinear() {
+    for depth in [20usize, 1000] {
+        let input = format!("{}....{}", "(".repeat(depth), ")".repeat(depth));
+        let r = rnadraw_core::layout(&input).unwrap();
+        let (x0, y0) = pair_midpoint(&r, 0);
+        let (x1, y1) = pair_midpoint(&r, depth - 1);
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let len = dx.hypot(dy);
+        let off_axis = |(x, y): (f64, f64)| ((x - x0) * dy - (y - y0) * dx).abs() / len;
+        let hairpin = &r.layout.loops[r.loop_index_of_pair(depth - 1, depth + 4).unwrap()];
+        let worst = (0..depth)
+            .map(|k| off_axis(pair_midpoint(&r, k)))
+            .fold(off_axis((hairpin.x, hairpin.y)), f64::max);
+        assert!(worst < 1e-9, "depth {depth}: off-axis by {worst}");
+    }
+}
+
+#[test]
+fn test_unpaired_bias_clusters_bases() {
+    let input = "((.......))";
+    let default = rnadraw_core::layout(input).unwrap();
+    let opts = LayoutOptions {
+        unpaired_bias: 0.5,
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+    let step = |r: &rnadraw_core::DrawResult, i: usize| {
+        let (a, b) = (&r.layout.bases[i], &r.layout.bases[i + 1]);
+        (a.x - b.x).hypot(a.y - b.y)
+    };
+    // Steps next to the closing pair shrink, the middle ones grow
+    assert!(step(&r, 1) < step(&default, 1));
+    assert!(step(&r, 8) < step(&default, 8));
+    assert!(step(&r, 5) > step(&default, 5));
+    // Pairs and the loop circle are unchanged
+    for i in [0, 1, 9, 10] {
+        let (a, b) = (&r.layout.bases[i], &default.layout.bases[i]);
+        assert!(close((a.x, a.y), (b.x, b.y)));
+    }
+}
+
+#[test]
+fn test_stem_compaction() {
+    let input = "((((((((((((....))))))))))))";
+    let default = rnadraw_core::layout(input).unwrap();
+    let opts = LayoutOptions {
+        stem_compaction: Some(4),
+        ..LayoutOptions::default()
+    };
+    let r = rnadraw_core::layout_with_options(input, &opts).unwrap();
+    let rise = |r: &rnadraw_core::DrawResult, i: usize, j: usize| {
+        let ((x1, y1), (x2, y2)) = (pair_midpoint(r, i), pair_midpoint(r, j));
+        (x1 - x2).hypot(y1 - y2)
+    };
+    // Two pairs at each end keep their spacing; the eight between them fit in one step
+    let step = rise(&default, 0, 1);
+    for (i, j) in [(0, 1), (10, 11)] {
+        assert!((rise(&r, i, j) - step).abs() < 1e-9);
+    }
+    assert!((rise(&r, 1, 10) - step).abs() < 1e-9);
+    assert!((rise(&r, 0, 11) - 3.0 * step).abs() < 1e-9);
+    // The hairpin is unchanged and still closes the helix
+    assert!(close(
+        (
+            r.layout.bases[12].x - r.layout.bases[11].x,
+            r.layout.bases[12].y - r.layout.bases[11].y
+        ),
+        (
+            default.layout.bases[12].x - default.layout.bases[11].x,
+            default.layout.bases[12].y - default.layout.bases[11].y
+        )
+    ));
+
+    // Helices within the limit are untouched
+    let short = rnadraw_core::layout_with_options("((((....))))", &opts).unwrap();
+    let plain = rnadraw_core::layout("((((....))))").unwrap();
+    assert_eq!(short.layout_hash(), plain.layout_hash());
+}
@@ -1,3 +1,4 @@
+use rnadraw_core::LayoutOptions;
 use rnadraw_core::svg::{SvgOptions, probability_to_color as prob_to_color};
 use wasm_bindgen::prelude::*;
 
@@ -13,6 +14,27 @@ pub fn draw_structure(input: &str) -> String {
     rnadraw_core::draw_structure(input)
 }
 
+/// Compute structure layout and return JSON, in screen coordinates if `y_down`.
+#[wasm_bindgen]
+pub fn draw_structure_oriented(input: &str, y_down: bool) -> String {
+    rnadraw_core::draw_structure_oriented(input, y_down)
+}
+
+/// Compute structure layout and return JSON with `"type"`-tagged segments.
+#[wasm_bindgen]
+pub fn draw_structure_tagged(input: &str) -> String {
+    rnadraw_core::draw_structure_tagged(input)
+}
+
+/// Compute structure layout with JSON layout options and return JSON.
+///
+/// `opts_json` is parsed as `LayoutOptions` with `#[serde(default)]`.
+#[wasm_bindgen]
+pub fn draw_structure_with_options(input: &str, opts_json: &str) -> String {
+    let opts: LayoutOptions = serde_json::from_str(opts_json).unwrap_or_default();
+    rnadraw_core::draw_structure_with_options(input, &opts)
+}
+
 /// Render structure as SVG with sequence and JSON options.
 ///
 /// `opts_json` is parsed as `SvgOptions` with `#[serde(default)]`,